@@ -0,0 +1,32 @@
+use std::env;
+use std::fs::{read_dir, write};
+use std::path::Path;
+
+/// Generates `builtin_lessons.rs`, a list of every lesson file in `lessons/` included with
+/// `include_str!` so the binary works without the lessons directory next to it.
+fn main() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let lessons_dir = Path::new(&manifest_dir).join("lessons");
+
+    println!("cargo:rerun-if-changed={}", lessons_dir.display());
+
+    let mut files: Vec<String> = read_dir(&lessons_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.ends_with(".txt"))
+        .collect();
+    files.sort();
+
+    let mut source = String::from("pub static BUILTIN_LESSONS: &[(&str, &str)] = &[\n");
+    for file in files {
+        source.push_str(&format!(
+            "    ({:?}, include_str!({:?})),\n",
+            file,
+            lessons_dir.join(&file).display().to_string()
+        ));
+    }
+    source.push_str("];\n");
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    write(Path::new(&out_dir).join("builtin_lessons.rs"), source).unwrap();
+}
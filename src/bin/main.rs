@@ -1,5 +1,8 @@
 extern crate programmer_dvorak_tutor;
 
 fn main() {
-    programmer_dvorak_tutor::create_app();
+    if let Err(error) = programmer_dvorak_tutor::create_app() {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
 }
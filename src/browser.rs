@@ -0,0 +1,196 @@
+use crate::stats::{LessonProgress, Stats};
+use crate::Lesson;
+use indexmap::map::IndexMap;
+
+/// How many entries page up and page down move.
+pub const PAGE_SIZE: usize = 10;
+
+/// A lesson as listed in the browser.
+#[derive(Debug, Clone)]
+pub struct BrowserEntry {
+    pub id: String,
+    pub title: String,
+    /// What the user did in the lesson so far, `None` if it was never tried.
+    pub progress: Option<LessonProgress>,
+}
+
+/// A key the browser reacts to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BrowserKey {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Character(char),
+    Backspace,
+    Enter,
+    Escape,
+}
+
+/// What a key did.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BrowserAction {
+    /// The selection or the filter changed, or nothing did.
+    Moved,
+    /// The user picked the lesson with this id.
+    Open(String),
+    Quit,
+}
+
+/// The state of the lesson picker shown when the tool runs without arguments: every lesson,
+/// narrowed down by a filter the user types, with one of them selected.
+///
+/// Like `TypingSession` it only handles keys, drawing is up to the terminal.
+#[derive(Debug, Clone)]
+pub struct LessonBrowser {
+    entries: Vec<BrowserEntry>,
+    filter: String,
+    /// Index into the entries matching the filter.
+    selected: usize,
+}
+
+impl LessonBrowser {
+    /// Lists `lessons` in order, with the selection on the first lesson that wasn't
+    /// completed yet.
+    pub fn new(lessons: &IndexMap<String, Lesson>, stats: &Stats) -> LessonBrowser {
+        let entries: Vec<BrowserEntry> = lessons
+            .values()
+            .map(|lesson| BrowserEntry {
+                id: lesson.id().to_string(),
+                title: lesson.title().to_string(),
+                progress: stats.lessons.get(lesson.id()).cloned(),
+            })
+            .collect();
+
+        let selected = entries
+            .iter()
+            .position(|entry| !entry.progress.as_ref().is_some_and(|p| p.completed))
+            .unwrap_or(0);
+
+        LessonBrowser {
+            entries,
+            filter: String::new(),
+            selected,
+        }
+    }
+
+    /// Selects the lesson with id `lesson_id`, if it's listed.
+    pub fn select(&mut self, lesson_id: &str) {
+        if let Some(index) = self
+            .visible()
+            .iter()
+            .position(|entry| entry.id == lesson_id)
+        {
+            self.selected = index;
+        }
+    }
+
+    /// Takes the progress of every lesson from `stats` again, after running lessons.
+    pub fn update_progress(&mut self, stats: &Stats) {
+        for entry in &mut self.entries {
+            entry.progress = stats.lessons.get(&entry.id).cloned();
+        }
+    }
+
+    /// The text typed to narrow down the list.
+    pub fn filter(&self) -> &str {
+        &self.filter
+    }
+
+    /// The entries whose id or title contain every word of the filter, ignoring case.
+    pub fn visible(&self) -> Vec<&BrowserEntry> {
+        let filter = self.filter.to_lowercase();
+
+        self.entries
+            .iter()
+            .filter(|entry| {
+                let text = format!("{} {}", entry.id, entry.title).to_lowercase();
+                filter.split_whitespace().all(|word| text.contains(word))
+            })
+            .collect()
+    }
+
+    /// Index of the selected entry in `visible`.
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
+
+    pub fn selected(&self) -> Option<&BrowserEntry> {
+        self.visible().get(self.selected).cloned()
+    }
+
+    pub fn handle(&mut self, key: BrowserKey) -> BrowserAction {
+        match key {
+            BrowserKey::Up => self.selected = self.selected.saturating_sub(1),
+            BrowserKey::Down => self.selected += 1,
+            BrowserKey::PageUp => self.selected = self.selected.saturating_sub(PAGE_SIZE),
+            BrowserKey::PageDown => self.selected += PAGE_SIZE,
+            BrowserKey::Character(character) => {
+                self.filter.push(character);
+                self.selected = 0;
+            }
+            BrowserKey::Backspace => {
+                self.filter.pop();
+                self.selected = 0;
+            }
+            BrowserKey::Enter => {
+                if let Some(entry) = self.selected() {
+                    return BrowserAction::Open(entry.id.clone());
+                }
+            }
+            // the first escape clears the filter, the second one quits
+            BrowserKey::Escape if !self.filter.is_empty() => {
+                self.filter.clear();
+                self.selected = 0;
+            }
+            BrowserKey::Escape => return BrowserAction::Quit,
+        }
+
+        self.selected = self.selected.min(self.visible().len().saturating_sub(1));
+        BrowserAction::Moved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LessonMeta;
+
+    #[test]
+    fn the_browser_filters_and_opens_lessons() {
+        let mut lessons = IndexMap::new();
+        for (id, title) in &[
+            ("01", "Home row"),
+            ("02", "Top row"),
+            ("03", "Home row again"),
+        ] {
+            let lesson = Lesson::new(
+                id.to_string(),
+                title.to_string(),
+                "uh".to_string(),
+                LessonMeta::default(),
+            );
+            lessons.insert(id.to_string(), lesson);
+        }
+        let mut stats = Stats::default();
+        stats.lessons.entry("01".to_string()).or_default().completed = true;
+
+        let mut browser = LessonBrowser::new(&lessons, &stats);
+        assert_eq!(browser.selected().unwrap().id, "02");
+
+        for character in "home".chars() {
+            browser.handle(BrowserKey::Character(character));
+        }
+        browser.handle(BrowserKey::Down);
+        browser.handle(BrowserKey::Down);
+        assert_eq!(browser.visible().len(), 2);
+        assert_eq!(
+            browser.handle(BrowserKey::Enter),
+            BrowserAction::Open("03".to_string())
+        );
+
+        assert_eq!(browser.handle(BrowserKey::Escape), BrowserAction::Moved);
+        assert_eq!(browser.visible().len(), 3);
+        assert_eq!(browser.handle(BrowserKey::Escape), BrowserAction::Quit);
+    }
+}
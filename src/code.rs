@@ -0,0 +1,349 @@
+use crate::text;
+use crate::{Lesson, LessonMeta};
+use std::path::Path;
+use std::str::FromStr;
+
+/// The most lines a snippet has, longer blocks of code are split.
+pub const SNIPPET_LINES: usize = 12;
+
+/// Blocks of code with fewer characters than this, not counting indentation, are too short
+/// to practice on, like a lone closing brace.
+const MIN_SNIPPET_CHARS: usize = 20;
+
+/// A programming language source files can be practiced in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Rust,
+    Python,
+    C,
+    JavaScript,
+    Shell,
+}
+
+/// What a character of source code is part of, for highlighting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Plain,
+    Keyword,
+    String,
+    Number,
+    Comment,
+}
+
+/// The parts of a language's syntax that matter for highlighting.
+struct Syntax {
+    line_comment: &'static str,
+    /// Delimiters of comments and strings that can span lines, with what they hold.
+    blocks: &'static [(&'static str, &'static str, TokenKind)],
+    quotes: &'static [char],
+    keywords: &'static [&'static str],
+}
+
+const RUST: Syntax = Syntax {
+    line_comment: "//",
+    blocks: &[("/*", "*/", TokenKind::Comment)],
+    // single quotes are left out, they start lifetimes as often as characters
+    quotes: &['"'],
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+        "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+        "true", "type", "unsafe", "use", "where", "while",
+    ],
+};
+
+const PYTHON: Syntax = Syntax {
+    line_comment: "#",
+    blocks: &[
+        ("\"\"\"", "\"\"\"", TokenKind::String),
+        ("'''", "'''", TokenKind::String),
+    ],
+    quotes: &['"', '\''],
+    keywords: &[
+        "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+        "elif", "else", "except", "False", "finally", "for", "from", "global", "if", "import",
+        "in", "is", "lambda", "None", "nonlocal", "not", "or", "pass", "raise", "return", "True",
+        "try", "while", "with", "yield",
+    ],
+};
+
+const C: Syntax = Syntax {
+    line_comment: "//",
+    blocks: &[("/*", "*/", TokenKind::Comment)],
+    quotes: &['"', '\''],
+    keywords: &[
+        "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else",
+        "enum", "extern", "float", "for", "goto", "if", "inline", "int", "long", "register",
+        "return", "short", "signed", "sizeof", "static", "struct", "switch", "typedef", "union",
+        "unsigned", "void", "volatile", "while",
+    ],
+};
+
+const JAVASCRIPT: Syntax = Syntax {
+    line_comment: "//",
+    blocks: &[
+        ("/*", "*/", TokenKind::Comment),
+        ("`", "`", TokenKind::String),
+    ],
+    quotes: &['"', '\''],
+    keywords: &[
+        "async",
+        "await",
+        "break",
+        "case",
+        "catch",
+        "class",
+        "const",
+        "continue",
+        "default",
+        "delete",
+        "do",
+        "else",
+        "export",
+        "extends",
+        "false",
+        "finally",
+        "for",
+        "function",
+        "if",
+        "import",
+        "in",
+        "instanceof",
+        "let",
+        "new",
+        "null",
+        "of",
+        "return",
+        "static",
+        "super",
+        "switch",
+        "this",
+        "throw",
+        "true",
+        "try",
+        "typeof",
+        "undefined",
+        "var",
+        "void",
+        "while",
+        "yield",
+    ],
+};
+
+const SHELL: Syntax = Syntax {
+    line_comment: "#",
+    blocks: &[],
+    quotes: &['"', '\''],
+    keywords: &[
+        "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+        "in", "local", "return", "then", "until", "while",
+    ],
+};
+
+impl Language {
+    /// The language of a source file, going by its extension.
+    pub fn from_path(path: &Path) -> Option<Language> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+
+        match extension.as_str() {
+            "rs" => Some(Language::Rust),
+            "py" => Some(Language::Python),
+            "c" | "h" | "cc" | "cpp" | "hpp" => Some(Language::C),
+            "js" | "mjs" | "cjs" | "jsx" | "ts" | "tsx" => Some(Language::JavaScript),
+            "sh" | "bash" | "zsh" => Some(Language::Shell),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::Rust => "Rust",
+            Language::Python => "Python",
+            Language::C => "C",
+            Language::JavaScript => "JavaScript",
+            Language::Shell => "Shell",
+        }
+    }
+
+    fn syntax(self) -> &'static Syntax {
+        match self {
+            Language::Rust => &RUST,
+            Language::Python => &PYTHON,
+            Language::C => &C,
+            Language::JavaScript => &JAVASCRIPT,
+            Language::Shell => &SHELL,
+        }
+    }
+}
+
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Language, String> {
+        match name.to_lowercase().as_str() {
+            "rust" | "rs" => Ok(Language::Rust),
+            "python" | "py" => Ok(Language::Python),
+            "c" | "c++" | "cpp" => Ok(Language::C),
+            "javascript" | "js" | "typescript" | "ts" => Ok(Language::JavaScript),
+            "shell" | "sh" | "bash" => Ok(Language::Shell),
+            _ => Err(format!(
+                "unknown language \"{}\", expected rust, python, c, javascript or shell",
+                name
+            )),
+        }
+    }
+}
+
+/// The kind of every character of `source`, one list per line.
+///
+/// This is a highlighter, not a parser: it knows comments, strings, numbers and keywords and
+/// treats everything else as plain text.
+pub fn highlight(language: Language, source: &str) -> Vec<Vec<TokenKind>> {
+    let syntax = language.syntax();
+    // the end delimiter and kind of a comment or string still open from an earlier line
+    let mut open_block: Option<(&str, TokenKind)> = None;
+
+    source
+        .lines()
+        .map(|line| {
+            let chars: Vec<char> = line.chars().collect();
+            let mut kinds = vec![TokenKind::Plain; chars.len()];
+            let mut index = 0;
+
+            while index < chars.len() {
+                let rest: String = chars[index..].iter().collect();
+
+                if let Some((end, kind)) = open_block {
+                    let length = match rest.find(end) {
+                        Some(position) => {
+                            open_block = None;
+                            rest[..position].chars().count() + end.chars().count()
+                        }
+                        None => chars.len() - index,
+                    };
+                    kinds[index..index + length].fill(kind);
+                    index += length;
+                } else if let Some(&(start, end, kind)) = syntax
+                    .blocks
+                    .iter()
+                    .find(|(start, _, _)| rest.starts_with(start))
+                {
+                    let length = start.chars().count();
+                    kinds[index..index + length].fill(kind);
+                    index += length;
+                    open_block = Some((end, kind));
+                } else if rest.starts_with(syntax.line_comment)
+                    && (syntax.line_comment != "#" || index == 0 || chars[index - 1] == ' ')
+                {
+                    // a # only starts a comment at the start of a word, `$#` is a variable
+                    kinds[index..].fill(TokenKind::Comment);
+                    index = chars.len();
+                } else if syntax.quotes.contains(&chars[index]) {
+                    let quote = chars[index];
+                    let mut end = index + 1;
+                    while end < chars.len() && chars[end] != quote {
+                        end += if chars[end] == '\\' { 2 } else { 1 };
+                    }
+                    let end = (end + 1).min(chars.len());
+                    kinds[index..end].fill(TokenKind::String);
+                    index = end;
+                } else if chars[index].is_alphanumeric() || chars[index] == '_' {
+                    let end = index
+                        + chars[index..]
+                            .iter()
+                            .take_while(|&&c| c.is_alphanumeric() || c == '_')
+                            .count();
+                    let word: String = chars[index..end].iter().collect();
+                    if chars[index].is_ascii_digit() {
+                        kinds[index..end].fill(TokenKind::Number);
+                    } else if syntax.keywords.contains(&word.as_str()) {
+                        kinds[index..end].fill(TokenKind::Keyword);
+                    }
+                    index = end;
+                } else {
+                    index += 1;
+                }
+            }
+
+            kinds
+        })
+        .collect()
+}
+
+/// A block of code to practice and where it starts in its file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snippet {
+    /// The line number of the first line, counting from 1.
+    pub line: usize,
+    pub text: String,
+}
+
+/// Picks blocks of code to practice out of a source file.
+///
+/// Blocks are separated by empty lines and split after `SNIPPET_LINES` lines. Tabs become
+/// spaces like in `text::practice_lines`, and the indentation all lines of a block share is
+/// removed. Blocks that are only comments, too short, or have lines wider than `width` are
+/// left out.
+pub fn snippets(language: Language, source: &str, width: usize) -> Vec<Snippet> {
+    let lines: Vec<String> = source.lines().map(text::clean_line).collect();
+    let kinds = highlight(language, &lines.join("\n"));
+
+    let mut blocks: Vec<(usize, Vec<usize>)> = Vec::new();
+    let mut block: Vec<usize> = Vec::new();
+    for index in 0..=lines.len() {
+        if index < lines.len() && !lines[index].is_empty() && block.len() < SNIPPET_LINES {
+            block.push(index);
+            continue;
+        }
+        if !block.is_empty() {
+            blocks.push((block[0], std::mem::take(&mut block)));
+        }
+        if index < lines.len() && !lines[index].is_empty() {
+            block.push(index);
+        }
+    }
+
+    blocks
+        .into_iter()
+        .filter(|(_, block)| {
+            let code_chars = block
+                .iter()
+                .flat_map(|&index| lines[index].chars().zip(&kinds[index]))
+                .filter(|(character, _)| !character.is_whitespace())
+                .collect::<Vec<_>>();
+
+            code_chars.len() >= MIN_SNIPPET_CHARS
+                && code_chars
+                    .iter()
+                    .any(|(_, &kind)| kind != TokenKind::Comment)
+        })
+        .filter_map(|(first, block)| {
+            let indent = block
+                .iter()
+                .map(|&index| lines[index].chars().take_while(|&c| c == ' ').count())
+                .min()
+                .unwrap_or(0);
+            let block: Vec<&str> = block.iter().map(|&index| &lines[index][indent..]).collect();
+
+            if block.iter().any(|line| line.chars().count() > width) {
+                return None;
+            }
+
+            Some(Snippet {
+                line: first + 1,
+                text: block.join("\n"),
+            })
+        })
+        .collect()
+}
+
+/// Makes a lesson of a snippet, with indentation typed automatically.
+pub fn code_lesson(language: Language, title: String, snippet: &Snippet) -> Lesson {
+    let meta = LessonMeta {
+        language: Some(language.name().to_string()),
+        auto_indent: true,
+        ..LessonMeta::default()
+    };
+
+    Lesson::new("code".to_string(), title, snippet.text.clone(), meta)
+}
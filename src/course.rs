@@ -0,0 +1,295 @@
+use crate::stats::Stats;
+use crate::Lesson;
+use indexmap::map::IndexMap;
+
+/// The id of the unit lessons that aren't part of any other unit are put in.
+pub const OTHER_UNIT: &str = "other";
+
+/// A group of lessons that practice the same keys.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unit {
+    pub id: String,
+    pub title: String,
+    /// The lessons to pass to complete the unit, in order.
+    pub lessons: Vec<String>,
+    /// Optional lessons with more practice of the same keys.
+    pub review: Vec<String>,
+    /// Ids of the units to complete before this one is unlocked.
+    pub prerequisites: Vec<String>,
+}
+
+/// The lessons as a course of units, each one unlocked once the units it builds on are
+/// completed.
+#[derive(Debug, Clone, Default)]
+pub struct Course {
+    units: Vec<Unit>,
+}
+
+impl Course {
+    /// A course of `units` over `lessons`. Lessons of the units that aren't in `lessons` are
+    /// left out, and lessons that aren't in any unit are added, in order, in a last unit
+    /// without prerequisites.
+    pub fn new(mut units: Vec<Unit>, lessons: &IndexMap<String, Lesson>) -> Course {
+        for unit in &mut units {
+            unit.lessons.retain(|id| lessons.contains_key(id));
+            unit.review.retain(|id| lessons.contains_key(id));
+        }
+
+        let other: Vec<String> = lessons
+            .keys()
+            .filter(|id| {
+                !units
+                    .iter()
+                    .any(|unit| unit.lessons.contains(id) || unit.review.contains(id))
+            })
+            .cloned()
+            .collect();
+
+        if !other.is_empty() {
+            units.push(Unit {
+                id: OTHER_UNIT.to_string(),
+                title: "Other lessons".to_string(),
+                lessons: other,
+                review: Vec::new(),
+                prerequisites: Vec::new(),
+            });
+        }
+
+        Course { units }
+    }
+
+    pub fn units(&self) -> &[Unit] {
+        &self.units
+    }
+
+    /// The unit a lesson is part of, as a lesson to pass or for review.
+    pub fn unit_of(&self, lesson_id: &str) -> Option<&Unit> {
+        self.units.iter().find(|unit| {
+            unit.lessons.iter().any(|id| id == lesson_id)
+                || unit.review.iter().any(|id| id == lesson_id)
+        })
+    }
+
+    /// Whether every lesson to pass in `unit` was passed.
+    pub fn is_unit_completed(&self, unit: &Unit, stats: &Stats) -> bool {
+        unit.lessons.iter().all(|id| is_passed(id, stats))
+    }
+
+    /// Whether every prerequisite of `unit` is completed. Prerequisites that don't exist
+    /// don't hold it back.
+    pub fn is_unit_unlocked(&self, unit: &Unit, stats: &Stats) -> bool {
+        unit.prerequisites.iter().all(|prerequisite| {
+            self.units
+                .iter()
+                .filter(|unit| unit.id == *prerequisite)
+                .all(|unit| self.is_unit_completed(unit, stats))
+        })
+    }
+
+    /// Whether the lesson's unit is unlocked and every lesson in the lesson's own
+    /// `prerequisites` passed.
+    pub fn is_unlocked(&self, lesson: &Lesson, stats: &Stats) -> bool {
+        let unit_unlocked = self
+            .unit_of(lesson.id())
+            .is_none_or(|unit| self.is_unit_unlocked(unit, stats));
+
+        unit_unlocked
+            && lesson
+                .meta()
+                .prerequisites
+                .iter()
+                .all(|id| is_passed(id, stats))
+    }
+
+    /// The lesson to go on with after `lesson_id`: the next unlocked lesson to pass in course
+    /// order, skipping review lessons.
+    pub fn next_lesson<'a>(
+        &'a self,
+        lesson_id: &str,
+        lessons: &IndexMap<String, Lesson>,
+        stats: &Stats,
+    ) -> Option<&'a str> {
+        let order = self.order();
+        let position = order.iter().position(|(id, _)| *id == lesson_id)?;
+
+        order[position + 1..]
+            .iter()
+            .filter(|(_, review)| !review)
+            .map(|(id, _)| *id)
+            .find(|id| self.is_unlocked_id(id, lessons, stats))
+    }
+
+    /// The lesson `--continue` starts with: the first unlocked lesson to pass that wasn't
+    /// passed yet, in course order.
+    pub fn continue_lesson<'a>(
+        &'a self,
+        lessons: &IndexMap<String, Lesson>,
+        stats: &Stats,
+    ) -> Option<&'a str> {
+        self.order()
+            .into_iter()
+            .filter(|(_, review)| !review)
+            .map(|(id, _)| id)
+            .find(|id| !is_passed(id, stats) && self.is_unlocked_id(id, lessons, stats))
+    }
+
+    /// Every lesson in course order, and whether it's a review lesson.
+    fn order(&self) -> Vec<(&str, bool)> {
+        self.units
+            .iter()
+            .flat_map(|unit| {
+                let lessons = unit.lessons.iter().map(|id| (id.as_str(), false));
+                let review = unit.review.iter().map(|id| (id.as_str(), true));
+                lessons.chain(review)
+            })
+            .collect()
+    }
+
+    fn is_unlocked_id(&self, id: &str, lessons: &IndexMap<String, Lesson>, stats: &Stats) -> bool {
+        lessons
+            .get(id)
+            .is_some_and(|lesson| self.is_unlocked(lesson, stats))
+    }
+}
+
+fn is_passed(lesson_id: &str, stats: &Stats) -> bool {
+    stats
+        .lessons
+        .get(lesson_id)
+        .is_some_and(|progress| progress.completed)
+}
+
+/// Parses a course manifest, units of `key: value` lines separated by empty lines:
+///
+/// ```text
+/// # comments start with a hash
+/// unit: home-u-h
+/// title: Home row: U and H
+/// lessons: 01
+///
+/// unit: home-o-n
+/// title: Home row: O and N
+/// prerequisites: home-u-h
+/// lessons: 04 05a
+/// review: 05b 05c
+/// ```
+///
+/// `lessons` and `review` list lesson ids. A unit without `prerequisites` builds on the unit
+/// before it, `prerequisites:` with nothing after it makes a unit that's unlocked right away.
+pub fn parse_course(source: &str) -> Result<Vec<Unit>, String> {
+    let mut units: Vec<Unit> = Vec::new();
+    // whether each unit said what its prerequisites are
+    let mut has_prerequisites: Vec<bool> = Vec::new();
+    let mut in_unit = false;
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+
+        if line.is_empty() {
+            in_unit = false;
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+
+        let (key, value) = match line.find(':') {
+            Some(colon) => (line[..colon].trim(), line[colon + 1..].trim()),
+            None => return Err(format!("line {}: expected \"key: value\"", line_number)),
+        };
+        let list = || value.split_whitespace().map(str::to_string);
+
+        if key == "unit" {
+            if value.is_empty() {
+                return Err(format!("line {}: the unit has no id", line_number));
+            }
+            if units.iter().any(|unit| unit.id == value) {
+                return Err(format!(
+                    "line {}: unit {} is defined twice",
+                    line_number, value
+                ));
+            }
+
+            units.push(Unit {
+                id: value.to_string(),
+                title: value.to_string(),
+                lessons: Vec::new(),
+                review: Vec::new(),
+                prerequisites: Vec::new(),
+            });
+            has_prerequisites.push(false);
+            in_unit = true;
+            continue;
+        }
+
+        let unit = match units.last_mut() {
+            Some(unit) if in_unit => unit,
+            _ => return Err(format!("line {}: expected \"unit: ID\" first", line_number)),
+        };
+
+        match key {
+            "title" => unit.title = value.to_string(),
+            "lessons" => unit.lessons.extend(list()),
+            "review" => unit.review.extend(list()),
+            "prerequisites" => {
+                unit.prerequisites.extend(list());
+                *has_prerequisites.last_mut().unwrap() = true;
+            }
+            _ => return Err(format!("line {}: unknown key \"{}\"", line_number, key)),
+        }
+    }
+
+    for index in 1..units.len() {
+        if !has_prerequisites[index] {
+            units[index].prerequisites = vec![units[index - 1].id.clone()];
+        }
+    }
+
+    Ok(units)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LessonMeta;
+
+    fn lesson_with_id(id: &str) -> Lesson {
+        Lesson::new(
+            id.to_string(),
+            format!("Lesson {}", id),
+            "uh".to_string(),
+            LessonMeta::default(),
+        )
+    }
+
+    #[test]
+    fn the_course_unlocks_units_once_their_prerequisites_are_passed() {
+        let units = parse_course(
+            "unit: first\nlessons: 01\n\nunit: second\nlessons: 02 03\nreview: 03b\n\n\
+             unit: side\nprerequisites: first\nlessons: 04\n",
+        )
+        .unwrap();
+        assert_eq!(units[1].prerequisites, vec!["first"]);
+        assert!(parse_course("lessons: 01").is_err());
+
+        let mut lessons = IndexMap::new();
+        for id in &["01", "02", "03", "03b", "04", "99"] {
+            lessons.insert(id.to_string(), lesson_with_id(id));
+        }
+        let course = Course::new(units, &lessons);
+        assert_eq!(course.units().last().unwrap().lessons, vec!["99"]);
+
+        let mut stats = Stats::default();
+        assert_eq!(course.continue_lesson(&lessons, &stats), Some("01"));
+        assert!(!course.is_unlocked(&lessons["02"], &stats));
+        // locked lessons are skipped, the side unit and the unit without prerequisites aren't
+        assert_eq!(course.next_lesson("01", &lessons, &stats), Some("99"));
+
+        stats.lessons.entry("01".to_string()).or_default().completed = true;
+        assert_eq!(course.next_lesson("01", &lessons, &stats), Some("02"));
+        // review lessons aren't needed to go on
+        assert_eq!(course.next_lesson("03", &lessons, &stats), Some("04"));
+        assert_eq!(course.continue_lesson(&lessons, &stats), Some("02"));
+    }
+}
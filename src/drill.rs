@@ -0,0 +1,356 @@
+use crate::stats::KeyStats;
+use crate::{Lesson, LessonMeta, Stats};
+use indexmap::map::IndexMap;
+use std::collections::BTreeSet;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Words and programmer tokens drills are built from.
+const WORDS: &str = include_str!("words.txt");
+
+/// How many lines a generated drill has.
+const DRILL_LINES: usize = 8;
+
+/// The longest a generated line may get.
+const LINE_WIDTH: usize = 48;
+
+/// Below this many usable words drills fall back to made up words.
+const MIN_WORDS: usize = 20;
+
+/// Timed tests get enough text to last at this many characters per second, 240 WPM.
+const TIMED_CHARS_PER_SECOND: f64 = 20.0;
+
+/// The longest timed test, an hour. Longer tests would need too much text.
+pub const MAX_TIMED_SECONDS: f64 = 3600.0;
+
+/// The keys introduced in every completed lesson, that is every key used in their text or
+/// listed in their front matter, plus the space bar.
+pub fn introduced_keys(lessons: &IndexMap<String, Lesson>, stats: &Stats) -> BTreeSet<char> {
+    let mut keys = BTreeSet::new();
+    keys.insert(' ');
+
+    for (id, lesson) in lessons {
+        let completed = stats
+            .lessons
+            .get(id)
+            .is_some_and(|progress| progress.completed);
+        if completed {
+            keys.extend(lesson.meta.keys.iter().cloned());
+            keys.extend(
+                lesson
+                    .text
+                    .chars()
+                    .filter(|character| !character.is_control()),
+            );
+        }
+    }
+
+    keys
+}
+
+/// Generates a practice lesson that over-samples the slowest and most missed keys and
+/// bigrams, only using keys from `keys`.
+pub fn generate_drill(stats: &Stats, keys: &BTreeSet<char>) -> Lesson {
+    drill_from(stats, keys, Random::from_time())
+}
+
+/// `generate_drill` with the random numbers taken from `random`.
+fn drill_from(stats: &Stats, keys: &BTreeSet<char>, mut random: Random) -> Lesson {
+    let weights = Weights::new(stats);
+
+    let words: Vec<&str> = WORDS
+        .split_whitespace()
+        .filter(|word| word.chars().all(|character| keys.contains(&character)))
+        .collect();
+
+    let candidates: Vec<String> = if words.len() >= MIN_WORDS {
+        words.iter().map(|word| word.to_string()).collect()
+    } else {
+        made_up_words(&weights, keys, &mut random)
+    };
+
+    let scores: Vec<f64> = candidates
+        .iter()
+        .map(|word| weights.word_score(word))
+        .collect();
+
+    let mut lines = Vec::with_capacity(DRILL_LINES);
+    for _ in 0..DRILL_LINES {
+        let mut line = String::new();
+        loop {
+            let word = &candidates[random.weighted_index(&scores)];
+            if !line.is_empty() && line.len() + 1 + word.len() > LINE_WIDTH {
+                break;
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+
+    let mut weakest: Vec<char> = keys.iter().cloned().filter(|key| *key != ' ').collect();
+    weakest.sort_by(|a, b| weights.key(*b).partial_cmp(&weights.key(*a)).unwrap());
+    weakest.truncate(5);
+
+    let meta = LessonMeta {
+        description: Some(format!(
+            "Focusing on: {}",
+            weakest.iter().collect::<String>()
+        )),
+        ..LessonMeta::default()
+    };
+
+    Lesson::new(
+        "adaptive".to_string(),
+        "Adaptive drill".to_string(),
+        lines.join("\n"),
+        meta,
+    )
+}
+
+/// Generates a timed test lasting `seconds`, random practice lines from `lessons`, or random
+/// words from `word_list` if one is given. There is more text than anyone could type in
+/// time, the test ends when the time runs out. Tests longer than `MAX_TIMED_SECONDS` only get
+/// text for that long.
+pub fn generate_timed_test(
+    lessons: &IndexMap<String, Lesson>,
+    word_list: Option<&str>,
+    seconds: f64,
+) -> Lesson {
+    let mut random = Random::from_time();
+    let wanted_chars = (seconds.min(MAX_TIMED_SECONDS) * TIMED_CHARS_PER_SECOND) as usize;
+
+    let corpus: Vec<&str> = lessons
+        .values()
+        .flat_map(|lesson| lesson.text.lines())
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+
+    let words: Vec<&str> = word_list.unwrap_or(WORDS).split_whitespace().collect();
+    let use_corpus = word_list.is_none() && !corpus.is_empty();
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut chars = 0;
+
+    while chars < wanted_chars {
+        let line = if use_corpus {
+            corpus[random.below(corpus.len())].to_string()
+        } else if !words.is_empty() {
+            random_line(&words, &mut random)
+        } else {
+            break;
+        };
+        chars += line.chars().count() + 1;
+        lines.push(line);
+    }
+
+    let meta = LessonMeta {
+        time_limit: Some(seconds),
+        ..LessonMeta::default()
+    };
+
+    Lesson::new(
+        "timed".to_string(),
+        format!("Timed test: {} seconds", seconds),
+        lines.join("\n"),
+        meta,
+    )
+}
+
+/// A line of random `words`, at most `LINE_WIDTH` long unless a single word is longer.
+fn random_line(words: &[&str], random: &mut Random) -> String {
+    let mut line = String::new();
+    loop {
+        let word = words[random.below(words.len())];
+        if !line.is_empty() && line.len() + 1 + word.len() > LINE_WIDTH {
+            return line;
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+}
+
+/// Practice words for when too few real words can be typed with the introduced keys, mostly
+/// built from the weakest keys.
+fn made_up_words(weights: &Weights, keys: &BTreeSet<char>, random: &mut Random) -> Vec<String> {
+    let keys: Vec<char> = keys.iter().cloned().filter(|key| *key != ' ').collect();
+    let key_weights: Vec<f64> = keys.iter().map(|key| weights.key(*key)).collect();
+
+    (0..MIN_WORDS * 2)
+        .map(|_| {
+            let length = 2 + random.below(4);
+            (0..length)
+                .map(|_| keys[random.weighted_index(&key_weights)])
+                .collect()
+        })
+        .collect()
+}
+
+/// How much practice each key and bigram needs, derived from the recorded statistics.
+struct Weights<'a> {
+    stats: &'a Stats,
+    average_latency_ms: f64,
+}
+
+impl<'a> Weights<'a> {
+    fn new(stats: &'a Stats) -> Weights<'a> {
+        let timed: Vec<&KeyStats> = stats
+            .keys
+            .values()
+            .filter(|key| key.latency_samples > 0)
+            .collect();
+
+        let average_latency_ms = if timed.is_empty() {
+            0.0
+        } else {
+            timed.iter().map(|key| key.average_latency_ms).sum::<f64>() / timed.len() as f64
+        };
+
+        Weights {
+            stats,
+            average_latency_ms,
+        }
+    }
+
+    /// 1 for a key typed accurately at average speed, more for slower or more missed keys.
+    fn of(&self, stats: Option<&KeyStats>) -> f64 {
+        let stats = match stats {
+            Some(stats) if stats.attempts() > 0 => stats,
+            // keys without data get a little extra practice so they get measured
+            _ => return 1.5,
+        };
+
+        let mut weight = 1.0 + stats.error_rate() / 10.0;
+        if self.average_latency_ms > 0.0 && stats.latency_samples > 0 {
+            weight *= (stats.average_latency_ms / self.average_latency_ms).max(0.5);
+        }
+        weight
+    }
+
+    fn key(&self, key: char) -> f64 {
+        self.of(self.stats.keys.get(&key))
+    }
+
+    fn bigram(&self, bigram: &str) -> f64 {
+        match self.stats.bigrams.get(bigram) {
+            Some(stats) => self.of(Some(stats)),
+            None => 1.0,
+        }
+    }
+
+    /// The average key and bigram weight of a word, squared to favour the weakest words.
+    fn word_score(&self, word: &str) -> f64 {
+        let chars: Vec<char> = word.chars().collect();
+
+        let keys: f64 = chars.iter().map(|key| self.key(*key)).sum();
+        let bigrams: f64 = chars
+            .windows(2)
+            .map(|pair| self.bigram(&pair.iter().collect::<String>()))
+            .sum();
+
+        let score = (keys + bigrams) / (2 * chars.len()).max(1) as f64;
+        score * score
+    }
+}
+
+/// A small xorshift generator, drills only need to look random.
+struct Random(u64);
+
+impl Random {
+    fn from_time() -> Random {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_nanos() as u64)
+            .unwrap_or(0);
+        Random(nanos | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    /// Picks an index with a probability proportional to its weight.
+    fn weighted_index(&mut self, weights: &[f64]) -> usize {
+        let total: f64 = weights.iter().sum();
+        let mut target = (self.next() as f64 / u64::MAX as f64) * total;
+
+        for (index, weight) in weights.iter().enumerate() {
+            if target < *weight {
+                return index;
+            }
+            target -= weight;
+        }
+        weights.len() - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::LessonProgress;
+
+    fn lesson(id: &str, text: &str) -> Lesson {
+        Lesson::new(
+            id.to_string(),
+            format!("Lesson {}", id),
+            text.to_string(),
+            LessonMeta::default(),
+        )
+    }
+
+    #[test]
+    fn keys_are_introduced_by_completed_lessons() {
+        let mut lessons = IndexMap::new();
+        lessons.insert("01".to_string(), lesson("01", "uh hu"));
+        lessons.insert("02".to_string(), lesson("02", "et te"));
+
+        let mut stats = Stats::default();
+        let completed = LessonProgress {
+            completed: true,
+            ..LessonProgress::default()
+        };
+        stats.lessons.insert("01".to_string(), completed);
+        stats
+            .lessons
+            .insert("02".to_string(), LessonProgress::default());
+
+        let keys: String = introduced_keys(&lessons, &stats).into_iter().collect();
+        assert_eq!(keys, " hu");
+    }
+
+    #[test]
+    fn drills_only_use_introduced_keys() {
+        let mut stats = Stats::default();
+        stats.record_keystroke(None, 'e', 'a', None);
+
+        for keys in &[" aoeuhtns", " uh", " aoeuidhtns-,.;"] {
+            let keys: BTreeSet<char> = keys.chars().collect();
+            let drill = drill_from(&stats, &keys, Random(7));
+
+            assert_eq!(drill.text().lines().count(), DRILL_LINES);
+            for character in drill.text().chars().filter(|&c| c != '\n') {
+                assert!(keys.contains(&character), "{:?} in {:?}", character, keys);
+            }
+        }
+    }
+
+    #[test]
+    fn drills_are_the_same_for_the_same_seed() {
+        let stats = Stats::default();
+        let keys: BTreeSet<char> = " aoeuidhtns".chars().collect();
+
+        let drill = drill_from(&stats, &keys, Random(42));
+        assert_eq!(drill.text(), drill_from(&stats, &keys, Random(42)).text());
+        assert_ne!(drill.text(), drill_from(&stats, &keys, Random(43)).text());
+    }
+}
@@ -0,0 +1,113 @@
+use crate::ghost::GhostPosition;
+use crate::metrics::SessionMetrics;
+use crate::session::{TypingSession, Update};
+use crate::{Event, Lesson};
+use std::time::Instant;
+
+/// A user interface lessons can be run in, see `run_lesson`.
+///
+/// The frontend only draws and reads input, the lesson logic lives in `TypingSession`.
+/// `TerminalFrontend` is the implementation used by the command line tool.
+pub trait Frontend {
+    type Error;
+
+    /// Shows the title and description of a lesson that is about to start.
+    fn start_lesson(&mut self, lesson: &Lesson) -> Result<(), Self::Error>;
+
+    /// Shows the line the user has to type next, `session.line()`.
+    fn show_line(&mut self, session: &TypingSession) -> Result<(), Self::Error>;
+
+    /// Shows what `update` changed, like a typed character or the move to the next line.
+    fn show_update(&mut self, session: &TypingSession, update: Update) -> Result<(), Self::Error>;
+
+    /// Shows the live status, like the metrics so far and the character to type next.
+    fn show_status(&mut self, session: &TypingSession) -> Result<(), Self::Error>;
+
+    /// Shows where the ghost of a previous run is, when racing against one. Called whenever
+    /// the ghost moves and after every new line.
+    fn show_ghost(
+        &mut self,
+        _session: &TypingSession,
+        _ghost: GhostPosition,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// The next input event, or `None` if there is none yet. Called in a loop while a line
+    /// is being typed, so it shouldn't block for long.
+    fn next_event(&mut self) -> Result<Option<Event>, Self::Error>;
+
+    /// When the event last returned by `next_event` happened. Frontends that replay events
+    /// override this, live ones can keep the default of the current time.
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    /// Shows the results of a finished lesson, and why it wasn't passed if it wasn't.
+    fn show_summary(
+        &mut self,
+        metrics: &SessionMetrics,
+        failures: &[String],
+    ) -> Result<(), Self::Error>;
+
+    /// Asks whether to continue with the next lesson, or to try it again if it wasn't
+    /// passed. See `next_lesson_question`.
+    fn ask_next_lesson(&mut self, passed: bool) -> Result<bool, Self::Error>;
+}
+
+impl<F: Frontend + ?Sized> Frontend for &mut F {
+    type Error = F::Error;
+
+    fn start_lesson(&mut self, lesson: &Lesson) -> Result<(), Self::Error> {
+        (**self).start_lesson(lesson)
+    }
+
+    fn show_line(&mut self, session: &TypingSession) -> Result<(), Self::Error> {
+        (**self).show_line(session)
+    }
+
+    fn show_update(&mut self, session: &TypingSession, update: Update) -> Result<(), Self::Error> {
+        (**self).show_update(session, update)
+    }
+
+    fn show_status(&mut self, session: &TypingSession) -> Result<(), Self::Error> {
+        (**self).show_status(session)
+    }
+
+    fn show_ghost(
+        &mut self,
+        session: &TypingSession,
+        ghost: GhostPosition,
+    ) -> Result<(), Self::Error> {
+        (**self).show_ghost(session, ghost)
+    }
+
+    fn next_event(&mut self) -> Result<Option<Event>, Self::Error> {
+        (**self).next_event()
+    }
+
+    fn now(&self) -> Instant {
+        (**self).now()
+    }
+
+    fn show_summary(
+        &mut self,
+        metrics: &SessionMetrics,
+        failures: &[String],
+    ) -> Result<(), Self::Error> {
+        (**self).show_summary(metrics, failures)
+    }
+
+    fn ask_next_lesson(&mut self, passed: bool) -> Result<bool, Self::Error> {
+        (**self).ask_next_lesson(passed)
+    }
+}
+
+/// The question asked after a lesson, see `Frontend::ask_next_lesson`.
+pub fn next_lesson_question(passed: bool) -> &'static str {
+    if passed {
+        "Lesson finished, next lesson? (y/n)"
+    } else {
+        "Lesson not passed, try again? (y/n)"
+    }
+}
@@ -0,0 +1,100 @@
+use crate::session::{LoggedEvent, TypingSession};
+use crate::Lesson;
+use std::time::{Duration, Instant};
+
+/// Which recorded run of a lesson to race against with `--race`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RaceAgainst {
+    /// The fastest run that completed the lesson.
+    Best,
+    /// The most recent run that completed the lesson.
+    Last,
+}
+
+/// Where a ghost is in a lesson, `column` characters into line `line`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GhostPosition {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A previous run of a lesson, replayed at its original pace.
+#[derive(Debug, Clone)]
+pub struct Ghost {
+    /// Where the run was after each of its events, by time since its first keystroke.
+    positions: Vec<(Duration, GhostPosition)>,
+    completed: bool,
+}
+
+impl Ghost {
+    /// Replays the recorded `events` of a run of `lesson` to find out where it was when.
+    pub fn new(lesson: &Lesson, events: &[LoggedEvent]) -> Ghost {
+        let mut session = TypingSession::new(lesson);
+        let start = Instant::now();
+        let mut positions = Vec::new();
+
+        for logged in events {
+            let now = start + logged.time;
+            session.handle(logged.event, now);
+
+            // the race clock starts with the first keystroke, not the first event
+            let first_keystroke = match session.metrics().start() {
+                Some(first_keystroke) => first_keystroke,
+                None => continue,
+            };
+            positions.push((
+                now - first_keystroke,
+                GhostPosition {
+                    line: session.line_index(),
+                    column: session.cursor(),
+                },
+            ));
+        }
+
+        Ghost {
+            positions,
+            completed: session.is_completed(),
+        }
+    }
+
+    /// Picks the run to race against from recorded runs of `lesson`, oldest first. Only runs
+    /// that completed the lesson count.
+    pub fn choose(
+        lesson: &Lesson,
+        runs: &[Vec<LoggedEvent>],
+        against: RaceAgainst,
+    ) -> Option<Ghost> {
+        let mut completed = runs
+            .iter()
+            .map(|events| Ghost::new(lesson, events))
+            .filter(|ghost| ghost.completed);
+
+        match against {
+            RaceAgainst::Best => completed.min_by_key(Ghost::duration),
+            RaceAgainst::Last => completed.next_back(),
+        }
+    }
+
+    /// Whether the run typed every line of the lesson.
+    pub fn is_completed(&self) -> bool {
+        self.completed
+    }
+
+    /// How long the run took.
+    pub fn duration(&self) -> Duration {
+        self.positions
+            .last()
+            .map(|(time, _)| *time)
+            .unwrap_or_default()
+    }
+
+    /// Where the run was `elapsed` after its first keystroke.
+    pub fn position(&self, elapsed: Duration) -> GhostPosition {
+        self.positions
+            .iter()
+            .take_while(|(time, _)| *time <= elapsed)
+            .last()
+            .map(|(_, position)| *position)
+            .unwrap_or(GhostPosition { line: 0, column: 0 })
+    }
+}
@@ -0,0 +1,41 @@
+use crate::course::{parse_course, Course};
+use crate::io::lesson::{builtin_file, lesson_search_path};
+use crate::Lesson;
+use indexmap::map::IndexMap;
+use std::fs::read_to_string;
+use std::path::PathBuf;
+
+/// The course manifest, next to the lesson files.
+const COURSE_FILE: &str = "course.txt";
+
+/// Loads the course manifest of the last directory of the lesson search path that has one,
+/// or the built in one, see `parse_course` for the format. Without a manifest that can be
+/// read every lesson is in one unit, in `lessons` order.
+pub fn load_course(extra_dirs: &[PathBuf], lessons: &IndexMap<String, Lesson>) -> Course {
+    let path = lesson_search_path(extra_dirs)
+        .into_iter()
+        .map(|dir| dir.join(COURSE_FILE))
+        .rfind(|path| path.is_file());
+
+    let (source, name) = match path {
+        Some(path) => match read_to_string(&path) {
+            Ok(source) => (source, path.display().to_string()),
+            Err(error) => {
+                eprintln!("Skipping {}: {}", path.display(), error);
+                return Course::new(Vec::new(), lessons);
+            }
+        },
+        None => match builtin_file(COURSE_FILE) {
+            Some(source) => (source.to_string(), COURSE_FILE.to_string()),
+            None => return Course::new(Vec::new(), lessons),
+        },
+    };
+
+    match parse_course(&source) {
+        Ok(units) => Course::new(units, lessons),
+        Err(error) => {
+            eprintln!("Skipping {}: {}", name, error);
+            Course::new(Vec::new(), lessons)
+        }
+    }
+}
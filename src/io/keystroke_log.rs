@@ -0,0 +1,85 @@
+use crate::session::LoggedEvent;
+use crate::Event;
+use serde::{Deserialize, Serialize};
+use std::fs::{read_to_string, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The keystroke log, next to stats.json.
+const LOG_PATH: &str = "keystrokes.jsonl";
+
+/// One line of the keystroke log, see the README for the format.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LogLine {
+    /// When the session was logged, in milliseconds since the Unix epoch. All events of a
+    /// session share it.
+    pub session: u64,
+    pub lesson: String,
+    /// Microseconds since the first event of the session.
+    pub t: u64,
+    pub event: Event,
+    /// What the lesson expected when the event happened, a new line at the end of a line.
+    pub expected: char,
+}
+
+/// Appends the events of a lesson run to the keystroke log.
+pub fn append_log(lesson_id: &str, events: &[LoggedEvent]) -> std::io::Result<()> {
+    let session = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis() as u64)
+        .unwrap_or(0);
+
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(LOG_PATH)?;
+    let mut writer = BufWriter::new(file);
+
+    for logged in events {
+        let line = LogLine {
+            session,
+            lesson: lesson_id.to_string(),
+            t: logged.time.as_micros() as u64,
+            event: logged.event,
+            expected: logged.expected,
+        };
+        serde_json::to_writer(&mut writer, &line)?;
+        writer.write_all(b"\n")?;
+    }
+
+    writer.flush()
+}
+
+/// The logged runs of the lesson `lesson_id`, oldest first. Lines that can't be parsed are
+/// skipped.
+pub fn load_runs(lesson_id: &str) -> std::io::Result<Vec<Vec<LoggedEvent>>> {
+    if !Path::new(LOG_PATH).exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut runs: Vec<Vec<LoggedEvent>> = Vec::new();
+    let mut last_session = None;
+
+    for line in read_to_string(LOG_PATH)?.lines() {
+        let line: LogLine = match serde_json::from_str(line) {
+            Ok(line) => line,
+            Err(_) => continue,
+        };
+        if line.lesson != lesson_id {
+            continue;
+        }
+
+        if last_session != Some(line.session) {
+            runs.push(Vec::new());
+            last_session = Some(line.session);
+        }
+        runs.last_mut().unwrap().push(LoggedEvent {
+            time: Duration::from_micros(line.t),
+            event: line.event,
+            expected: line.expected,
+        });
+    }
+
+    Ok(runs)
+}
@@ -0,0 +1,81 @@
+use crate::io::lesson::config_dir;
+use crate::layout::{Layout, BUILTIN_LAYOUTS};
+use std::fs::read_to_string;
+use std::path::Path;
+
+/// Loads the layout for `--layout`, which is either the path of a layout definition file,
+/// the name of a file in the user's `layouts` config directory or a built in layout.
+pub fn load_layout(name: &str) -> Result<Layout, String> {
+    let path = Path::new(name);
+    if path.is_file() {
+        return load_layout_file(path);
+    }
+
+    if let Some(config_dir) = config_dir() {
+        let path = config_dir.join("layouts").join(format!("{}.layout", name));
+        if path.is_file() {
+            return load_layout_file(&path);
+        }
+    }
+
+    for (file_name, source) in BUILTIN_LAYOUTS {
+        let layout: Layout = source.parse().unwrap();
+        if *file_name == name || layout.is_named(name) {
+            return Ok(layout);
+        }
+    }
+
+    let names: Vec<&str> = BUILTIN_LAYOUTS.iter().map(|(name, _)| *name).collect();
+    Err(format!(
+        "unknown layout \"{}\", use a layout file or one of: {}",
+        name,
+        names.join(", ")
+    ))
+}
+
+fn load_layout_file(path: &Path) -> Result<Layout, String> {
+    let source = read_to_string(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+
+    source
+        .parse()
+        .map_err(|error| format!("{}: {}", path.display(), error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn loads_builtin_layouts_by_file_or_layout_name() {
+        assert_eq!(load_layout("colemak-dh").unwrap().name(), "Colemak-DH");
+        assert_eq!(
+            load_layout("Programmer Dvorak").unwrap().name(),
+            "Programmer Dvorak"
+        );
+
+        let error = load_layout("no-such-layout").unwrap_err();
+        assert!(error.starts_with("unknown layout \"no-such-layout\""));
+    }
+
+    #[test]
+    fn loads_layout_files_and_reports_their_errors() {
+        let dir = std::env::temp_dir().join(format!("tutor-layout-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tiny.layout");
+
+        fs::write(&path, "name: Tiny\nhome: a s d f\n").unwrap();
+        let layout = load_layout(path.to_str().unwrap()).unwrap();
+        assert_eq!(layout.name(), "Tiny");
+        assert_eq!(layout.key_for('f').unwrap().position.column, 4);
+
+        fs::write(&path, "name: Tiny\nhome a s d f\n").unwrap();
+        let error = load_layout(path.to_str().unwrap()).unwrap_err();
+        assert_eq!(
+            error,
+            format!("{}: line 2: expected \"key: value\"", path.display())
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,90 +1,481 @@
-use crate::Lesson;
-use indexmap::map::IndexMap;
-use std::cmp::Ordering;
-use std::cmp::Ordering::{Equal, Greater, Less};
-use std::fs::read_to_string;
-use walkdir::{DirEntry, WalkDir};
-
-pub fn load_lessons() -> IndexMap<String, Lesson> {
-    fn is_lesson(entry: &DirEntry) -> bool {
-        entry.file_name().to_str().unwrap().starts_with("lesson_")
-    }
-
-    let mut lessons: IndexMap<String, Lesson> = IndexMap::new();
-
-    let walker = WalkDir::new("lessons")
-        .sort_by(|a, b| {
-            if !is_lesson(a) {
-                return Less;
-            }
-            if !is_lesson(b) {
-                return Greater;
-            }
-
-            let id_a = a
-                .file_name()
-                .to_str()
-                .unwrap()
-                .trim_start_matches("lesson_")
-                .trim_end_matches(".txt");
-
-            let id_b = b
-                .file_name()
-                .to_str()
-                .unwrap()
-                .trim_start_matches("lesson_")
-                .trim_end_matches(".txt");
-
-            compare_lesson_id(id_a, id_b)
-        })
-        .contents_first(true)
-        .into_iter();
-
-    for entry in walker.filter_entry(|e| is_lesson(e) && e.file_type().is_file()) {
-        let entry = entry.unwrap();
-        let id = entry
-            .file_name()
-            .to_str()
-            .unwrap()
-            .trim_start_matches("lesson_")
-            .trim_end_matches(".txt");
-
-        let lesson_string = read_to_string(entry.path()).unwrap();
-        let lesson = Lesson::new(lesson_string, id.to_string());
-
-        lessons.insert(id.to_string(), lesson);
-    }
-    lessons
-}
-
-/* 0 - less than
- * 1 - greater than
- * 2 - equal to
- */
-fn compare_lesson_id(id_a: &str, id_b: &str) -> Ordering {
-    let letter_a = id_a.trim_matches(char::is_numeric);
-    let letter_b = id_a.trim_matches(char::is_numeric);
-
-    let number_a = id_a
-        .trim_matches(char::is_alphabetic)
-        .parse::<u32>()
-        .unwrap();
-    let number_b = id_b
-        .trim_matches(char::is_alphabetic)
-        .parse::<u32>()
-        .unwrap();
-
-    if number_a > number_b {
-        return Greater;
-    } else if number_a == number_b {
-        if letter_a.is_empty() || letter_b.is_empty() {
-            return Equal;
-        }
-        if letter_a > letter_b {
-            return Greater;
-        }
-        return Less;
-    } else {
-        return Less;
-    }
-}
+use crate::code::Language;
+use crate::layout::{Finger, Row};
+use crate::lesson_id::LessonId;
+use crate::{Lesson, LessonFile, LessonMeta};
+use indexmap::map::IndexMap;
+use std::env;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+mod builtin {
+    include!(concat!(env!("OUT_DIR"), "/builtin_lessons.rs"));
+}
+
+/// Lessons in this directory, relative to the working directory, override or extend the
+/// lessons built into the binary.
+const LESSON_DIR: &str = "lessons";
+
+/// Directory for lessons installed system wide.
+const SYSTEM_LESSON_DIR: &str = "/usr/share/programmer-dvorak-tutor/lessons";
+
+/// The directories lessons are loaded from, lowest priority first, after the built in ones:
+///
+/// 1. the system wide directory, `/usr/share/programmer-dvorak-tutor/lessons`
+/// 2. the user directory, `$XDG_CONFIG_HOME/programmer-dvorak-tutor/lessons` (usually under
+///    `~/.config`, or `%APPDATA%` on Windows)
+/// 3. `lessons` in the working directory
+/// 4. every directory in `extra_dirs`, in order
+///
+/// Directories that don't exist are skipped.
+pub fn lesson_search_path(extra_dirs: &[PathBuf]) -> Vec<PathBuf> {
+    let mut dirs = vec![PathBuf::from(SYSTEM_LESSON_DIR)];
+
+    if let Some(config_dir) = config_dir() {
+        dirs.push(config_dir.join("lessons"));
+    }
+
+    dirs.push(PathBuf::from(LESSON_DIR));
+    dirs.extend(extra_dirs.iter().cloned());
+
+    dirs
+}
+
+/// The per-user configuration directory of the tutor.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+
+    Some(base.join("programmer-dvorak-tutor"))
+}
+
+/// Loads the built in lessons, then every directory of the search path on top of them.
+///
+/// A lesson whose id was already loaded replaces the earlier one, so a directory later in
+/// the search path wins. The result is ordered by lesson id no matter where the lessons came
+/// from, so extra lessons slot in between the built in ones.
+///
+/// If a `layout` is given only lessons for it are kept. Lessons that don't say which layout
+/// they are for are Programmer Dvorak lessons. If no lesson is for `layout` all of them are
+/// kept instead.
+///
+/// Files that can't be read or parsed, or are named like lessons without a valid id, are
+/// skipped. They are returned as errors starting with the file they are about.
+pub fn load_lessons(
+    extra_dirs: &[PathBuf],
+    layout: Option<&str>,
+) -> (IndexMap<String, Lesson>, Vec<String>) {
+    let mut lessons: IndexMap<String, Lesson> = IndexMap::new();
+    let mut errors = Vec::new();
+
+    for (file_name, lesson_string) in builtin::BUILTIN_LESSONS {
+        let result = match lesson_id(Path::new(file_name)) {
+            Some(Ok(id)) => insert_lesson(&mut lessons, &id, lesson_string, None),
+            Some(Err(error)) => Err(error),
+            None => Ok(()),
+        };
+        if let Err(error) = result {
+            errors.push(format!("{}: {}", file_name, error));
+        }
+    }
+
+    for dir in lesson_search_path(extra_dirs) {
+        if dir.is_dir() {
+            load_lesson_dir(&dir, &mut lessons, &mut errors);
+        }
+    }
+
+    if let Some(layout) = layout {
+        if lessons.values().any(|lesson| lesson.is_for_layout(layout)) {
+            lessons.retain(|_, lesson| lesson.is_for_layout(layout));
+        } else {
+            eprintln!(
+                "There are no lessons for {}, showing the lessons for other layouts",
+                layout
+            );
+        }
+    }
+
+    // every id was parsed when its lesson was inserted
+    lessons.sort_by(|id_a, _, id_b, _| {
+        let id_a = id_a.parse::<LessonId>().ok();
+        id_a.cmp(&id_b.parse::<LessonId>().ok())
+    });
+    (lessons, errors)
+}
+
+/// The contents of a file built in from the `lessons` directory, like the course manifest.
+pub fn builtin_file(file_name: &str) -> Option<&'static str> {
+    builtin::BUILTIN_LESSONS
+        .iter()
+        .find(|(name, _)| *name == file_name)
+        .map(|(_, contents)| *contents)
+}
+
+/// Adds every lesson file found in `dir` to `lessons`, replacing lessons with the same id.
+///
+/// Lessons in subdirectories get hierarchical ids, see `lesson_id`.
+fn load_lesson_dir(dir: &Path, lessons: &mut IndexMap<String, Lesson>, errors: &mut Vec<String>) {
+    let walker = WalkDir::new(dir).sort_by(|a, b| a.file_name().cmp(b.file_name()));
+
+    for entry in walker.into_iter().filter_map(Result::ok) {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let relative_path = entry.path().strip_prefix(dir).unwrap_or(entry.path());
+        let result = match lesson_id(relative_path) {
+            Some(Ok(id)) => read_to_string(entry.path())
+                .map_err(|error| error.to_string())
+                .and_then(|lesson_string| {
+                    insert_lesson(lessons, &id, &lesson_string, Some(entry.path()))
+                }),
+            Some(Err(error)) => Err(error),
+            None => Ok(()),
+        };
+
+        if let Err(error) = result {
+            errors.push(format!("{}: {}", entry.path().display(), error));
+        }
+    }
+}
+
+/// Parses a lesson and adds it to `lessons`, remembering `path` as the file it came from.
+fn insert_lesson(
+    lessons: &mut IndexMap<String, Lesson>,
+    id: &LessonId,
+    lesson_string: &str,
+    path: Option<&Path>,
+) -> Result<(), String> {
+    let mut lesson = parse_lesson(id.as_str(), lesson_string)?;
+
+    if let Some(path) = path {
+        // the practice text is every line after the title, joined with newlines
+        let text_lines = lesson.text.split('\n').count();
+        let text_line = (lesson_string.lines().count() + 1).saturating_sub(text_lines);
+        lesson.file = Some(LessonFile {
+            path: path.to_path_buf(),
+            text_line,
+        });
+    }
+
+    lessons.insert(id.to_string(), lesson);
+    Ok(())
+}
+
+/// The lesson id of a file named like `lesson_12b.txt`, given its path relative to the lesson
+/// directory. The subdirectories it's in come first, so `unit3/lesson_04.txt` is lesson
+/// `unit3/04`.
+///
+/// `None` if the file isn't a lesson, an error if it's named like one but the name isn't a
+/// valid id.
+fn lesson_id(relative_path: &Path) -> Option<Result<LessonId, String>> {
+    let file_name = relative_path.file_name()?.to_string_lossy();
+    let name = file_name.strip_prefix("lesson_")?.strip_suffix(".txt")?;
+
+    let mut parts: Vec<String> = relative_path
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    parts.push(name.to_string());
+
+    Some(parts.join("/").parse())
+}
+
+/// Parses a lesson file.
+///
+/// A lesson may start with a front matter block between two `---` lines holding
+/// `key: value` pairs:
+///
+/// ```text
+/// ---
+/// title: Lesson 01: Introducing U and H
+/// keys: u h
+/// fingers: left index, right index
+/// rows: home
+/// prerequisites: 00
+/// layout: Programmer Dvorak
+/// target_wpm: 15
+/// target_accuracy: 95
+/// max_errors: 3
+/// time_limit: 60
+/// language: rust
+/// auto_indent: true
+/// description: The first two home row keys.
+/// ---
+/// uuuu hhhh uuuu hhhh
+/// ```
+///
+/// Without front matter the first line is the title and the rest is practice text.
+pub fn parse_lesson(id: &str, source: &str) -> Result<Lesson, String> {
+    let mut lines = source.lines();
+    let mut meta = LessonMeta::default();
+    let mut title = None;
+
+    if source.lines().next().map(str::trim_end) == Some("---") {
+        lines.next();
+
+        let mut closed = false;
+        for (index, line) in lines.by_ref().enumerate() {
+            // front matter starts on the second line of the file
+            let line_number = index + 2;
+
+            if line.trim_end() == "---" {
+                closed = true;
+                break;
+            }
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = match line.find(':') {
+                Some(colon) => (line[..colon].trim(), line[colon + 1..].trim()),
+                None => return Err(format!("line {}: expected \"key: value\"", line_number)),
+            };
+
+            parse_meta_field(&mut meta, &mut title, key, value)
+                .map_err(|error| format!("line {}: {}", line_number, error))?;
+        }
+
+        if !closed {
+            return Err("front matter is missing its closing \"---\"".to_string());
+        }
+    }
+
+    let title = match title {
+        Some(title) => title,
+        None => lines
+            .next()
+            .ok_or_else(|| "lesson is empty".to_string())?
+            .to_string(),
+    };
+
+    let text = lines.collect::<Vec<&str>>().join("\n");
+
+    Ok(Lesson::new(id.to_string(), title, text, meta))
+}
+
+fn parse_meta_field(
+    meta: &mut LessonMeta,
+    title: &mut Option<String>,
+    key: &str,
+    value: &str,
+) -> Result<(), String> {
+    fn list(value: &str) -> impl Iterator<Item = &str> {
+        value.split([',', ' ']).filter(|item| !item.is_empty())
+    }
+
+    match key {
+        "title" => *title = Some(value.to_string()),
+        "keys" => {
+            for key in value.split_whitespace() {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    _ if key == "space" => meta.keys.push(' '),
+                    (Some(character), None) => meta.keys.push(character),
+                    _ => return Err(format!("\"{}\" is not a single key", key)),
+                }
+            }
+        }
+        "finger" | "fingers" => {
+            for finger in value.split(',').filter(|item| !item.trim().is_empty()) {
+                meta.fingers.push(finger.parse::<Finger>()?);
+            }
+        }
+        "row" | "rows" => {
+            for row in value.split(',').filter(|item| !item.trim().is_empty()) {
+                meta.rows.push(row.parse::<Row>()?);
+            }
+        }
+        "prerequisites" => meta.prerequisites.extend(list(value).map(str::to_string)),
+        "target_wpm" => {
+            meta.target_wpm = Some(
+                value
+                    .parse()
+                    .map_err(|_| format!("target_wpm \"{}\" is not a number", value))?,
+            )
+        }
+        "target_accuracy" => {
+            meta.target_accuracy = Some(
+                value
+                    .trim_end_matches('%')
+                    .parse()
+                    .map_err(|_| format!("target_accuracy \"{}\" is not a number", value))?,
+            )
+        }
+        "max_errors" => {
+            meta.max_errors = Some(
+                value
+                    .parse()
+                    .map_err(|_| format!("max_errors \"{}\" is not a whole number", value))?,
+            )
+        }
+        "time_limit" => {
+            meta.time_limit = Some(
+                value
+                    .trim_end_matches('s')
+                    .parse()
+                    .map_err(|_| format!("time_limit \"{}\" is not a number", value))?,
+            )
+        }
+        "layout" => meta.layout = Some(value.to_string()),
+        "language" => meta.language = Some(value.parse::<Language>()?.name().to_string()),
+        "auto_indent" => {
+            meta.auto_indent = value
+                .parse()
+                .map_err(|_| format!("auto_indent \"{}\" is not true or false", value))?
+        }
+        "description" => {
+            // repeated description lines are joined into one paragraph
+            meta.description = Some(match meta.description.take() {
+                Some(description) => format!("{} {}", description, value),
+                None => value.to_string(),
+            })
+        }
+        _ => return Err(format!("unknown front matter key \"{}\"", key)),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn parses_front_matter() {
+        let source = "---\n\
+                      title: Lesson 07: Introducing D\n\
+                      # comments and empty lines are skipped\n\
+                      \n\
+                      keys: d space\n\
+                      fingers: right middle, left index\n\
+                      rows: home\n\
+                      prerequisites: 05a, 06\n\
+                      target_accuracy: 95%\n\
+                      max_errors: 3\n\
+                      time_limit: 60s\n\
+                      language: py\n\
+                      auto_indent: true\n\
+                      description: The first line\n\
+                      description: and the second.\n\
+                      ---\n\
+                      dd dd\n\
+                      ud du";
+        let lesson = parse_lesson("07", source).unwrap();
+        let meta = lesson.meta();
+
+        assert_eq!(lesson.title(), "Lesson 07: Introducing D");
+        assert_eq!(lesson.text(), "dd dd\nud du");
+        assert_eq!(meta.keys, vec!['d', ' ']);
+        assert_eq!(meta.fingers, vec![Finger::RightMiddle, Finger::LeftIndex]);
+        assert_eq!(meta.rows, vec![Row::Home]);
+        assert_eq!(meta.prerequisites, vec!["05a", "06"]);
+        assert_eq!(meta.target_accuracy, Some(95.0));
+        assert_eq!(meta.target_wpm, None);
+        assert_eq!(meta.max_errors, Some(3));
+        assert_eq!(meta.time_limit, Some(60.0));
+        assert_eq!(meta.language.as_deref(), Some("Python"));
+        assert!(meta.auto_indent);
+        assert_eq!(
+            meta.description.as_deref(),
+            Some("The first line and the second.")
+        );
+    }
+
+    #[test]
+    fn takes_the_title_from_the_first_line_without_front_matter() {
+        let lesson = parse_lesson("01", "Lesson 01\nuh uh\nhu hu").unwrap();
+
+        assert_eq!(lesson.title(), "Lesson 01");
+        assert_eq!(lesson.text(), "uh uh\nhu hu");
+        assert!(lesson.meta().keys.is_empty());
+    }
+
+    #[test]
+    fn reports_front_matter_errors_with_their_line() {
+        let error = |source: &str| parse_lesson("01", source).err().unwrap();
+
+        assert_eq!(error(""), "lesson is empty");
+        assert_eq!(
+            error("---\ntitle: T\nkeys: u h"),
+            "front matter is missing its closing \"---\""
+        );
+        assert_eq!(
+            error("---\ntitle: T\nkeys uh\n---\nuh"),
+            "line 3: expected \"key: value\""
+        );
+        assert_eq!(
+            error("---\nkeys: uh\n---\nuh"),
+            "line 2: \"uh\" is not a single key"
+        );
+        assert_eq!(
+            error("---\ntarget_wpm: fast\n---\nuh"),
+            "line 2: target_wpm \"fast\" is not a number"
+        );
+        assert_eq!(
+            error("---\ncolor: red\n---\nuh"),
+            "line 2: unknown front matter key \"color\""
+        );
+    }
+
+    #[test]
+    fn ids_of_lesson_files_include_their_subdirectories() {
+        let id = |path: &str| lesson_id(Path::new(path)).map(|id| id.map(|id| id.to_string()));
+
+        assert_eq!(id("lesson_12b.txt"), Some(Ok("12b".to_string())));
+        assert_eq!(id("unit3/lesson_04.txt"), Some(Ok("unit3/04".to_string())));
+        assert_eq!(id("test_lesson.txt"), None);
+        assert_eq!(id("lesson_12.md"), None);
+        assert!(matches!(id("lesson_.txt"), Some(Err(_))));
+    }
+
+    #[test]
+    fn later_lesson_directories_override_earlier_ones() {
+        let dir = std::env::temp_dir().join(format!("tutor-lessons-{}", std::process::id()));
+        let (first, second) = (dir.join("first"), dir.join("second"));
+        fs::create_dir_all(first.join("extra")).unwrap();
+        fs::create_dir_all(&second).unwrap();
+
+        fs::write(first.join("lesson_01.txt"), "Replaced U and H\nuh\n").unwrap();
+        fs::write(first.join("extra/lesson_02.txt"), "First\nab\n").unwrap();
+        fs::write(first.join("lesson_x y.txt"), "Bad id\nab\n").unwrap();
+        fs::create_dir_all(second.join("extra")).unwrap();
+        fs::write(second.join("extra/lesson_02.txt"), "Second\ncd\n").unwrap();
+
+        let (lessons, errors) = load_lessons(&[first.clone(), second.clone()], None);
+
+        // a user lesson with the id of a built in one replaces it
+        let replaced = &lessons["01"];
+        assert_eq!(replaced.title(), "Replaced U and H");
+        assert_eq!(
+            replaced.file().map(|file| file.path.clone()),
+            Some(first.join("lesson_01.txt"))
+        );
+        assert_eq!(lessons["extra/02"].title(), "Second");
+        // and lessons are ordered by id wherever they came from
+        let ids: Vec<&String> = lessons.keys().collect();
+        let position = |id: &str| ids.iter().position(|other| *other == id).unwrap();
+        assert!(position("01") < position("02"));
+        assert!(position("02") < position("extra/02"));
+
+        assert!(
+            errors.iter().any(|error| error.contains("lesson_x y.txt")),
+            "{:?}",
+            errors
+        );
+
+        let search_path = lesson_search_path(&[first, second.clone()]);
+        assert_eq!(search_path[0], Path::new(SYSTEM_LESSON_DIR));
+        assert_eq!(search_path[search_path.len() - 3], Path::new(LESSON_DIR));
+        assert_eq!(search_path.last(), Some(&second));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,2 +1,8 @@
-pub mod lesson;
-pub mod stats;
\ No newline at end of file
+pub mod course;
+pub mod keystroke_log;
+pub mod layout;
+pub mod lesson;
+pub mod script;
+pub mod stats;
+pub mod text;
+pub mod xkb;
@@ -0,0 +1,10 @@
+use crate::script::{parse_script, ScriptedEvent};
+use std::fs::read_to_string;
+use std::path::Path;
+
+/// Loads the script given with `--script`, see `parse_script` for the format.
+pub fn load_script(path: &Path) -> Result<Vec<ScriptedEvent>, String> {
+    let source = read_to_string(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+
+    parse_script(&source).map_err(|error| format!("{}: {}", path.display(), error))
+}
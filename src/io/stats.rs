@@ -1,10 +1,81 @@
-use crate::Stats;
-use std::fs::read_to_string;
-
-pub fn load_stats() -> Stats {
-    let stats_json = read_to_string("stats.json").unwrap();
-
-    let stats: Stats = serde_json::from_str(stats_json.as_str()).unwrap();
-
-    stats
-}
\ No newline at end of file
+use crate::Stats;
+use std::fs::{read_to_string, rename, write};
+use std::path::{Path, PathBuf};
+
+const STATS_PATH: &str = "stats.json";
+
+/// Loads the saved statistics, falling back to empty ones if there are none yet.
+///
+/// A stats file that can't be read is moved to stats.json.bak rather than overwritten so
+/// it can still be recovered by hand.
+pub fn load_stats() -> Stats {
+    load_stats_from(Path::new(STATS_PATH))
+}
+
+fn load_stats_from(path: &Path) -> Stats {
+    if !path.exists() {
+        return Stats::default();
+    }
+
+    let stats = read_to_string(path)
+        .map_err(|error| error.to_string())
+        .and_then(|stats_json| {
+            serde_json::from_str::<Stats>(stats_json.as_str()).map_err(|error| error.to_string())
+        });
+
+    match stats {
+        Ok(stats) => stats,
+        Err(error) => {
+            eprintln!(
+                "Could not load {} ({}), starting over with empty stats",
+                path.display(),
+                error
+            );
+            let mut backup = path.as_os_str().to_owned();
+            backup.push(".bak");
+            let backup = PathBuf::from(backup);
+            if rename(path, &backup).is_ok() {
+                eprintln!("The old stats were moved to {}", backup.display());
+            }
+            Stats::default()
+        }
+    }
+}
+
+pub fn save_stats(stats: &Stats) -> std::io::Result<()> {
+    let stats_json = serde_json::to_string_pretty(stats)?;
+
+    write(STATS_PATH, stats_json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn moves_corrupt_stats_aside_and_starts_over() {
+        let dir = std::env::temp_dir().join(format!("tutor-stats-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("stats.json");
+        let backup = dir.join("stats.json.bak");
+        let _ = fs::remove_file(&backup);
+
+        assert!(load_stats_from(&path).lessons.is_empty());
+        assert!(!backup.exists());
+
+        fs::write(&path, "{\"last_lesson_id\": \"07\"}").unwrap();
+        assert_eq!(load_stats_from(&path).last_lesson_id, "07");
+
+        fs::write(&path, "{\"last_lesson_id\": ").unwrap();
+        let stats = load_stats_from(&path);
+        assert_eq!(stats.last_lesson_id, "");
+        assert!(!path.exists());
+        assert_eq!(
+            fs::read_to_string(&backup).unwrap(),
+            "{\"last_lesson_id\": "
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,48 @@
+use std::fs::read;
+use std::io::Read;
+use std::path::Path;
+
+/// Reads the text given with `--text`. Bytes that aren't valid UTF-8 are replaced.
+pub fn load_text(path: &Path) -> Result<String, String> {
+    let bytes = read(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Reads all of standard input for `--stdin`. If the lessons are typed on the `terminal` and
+/// standard input was piped in, it's then pointed at the terminal, so keys can be read and
+/// raw mode works as usual.
+pub fn read_stdin(terminal: bool) -> Result<String, String> {
+    let mut bytes = Vec::new();
+    std::io::stdin()
+        .read_to_end(&mut bytes)
+        .map_err(|error| format!("standard input: {}", error))?;
+
+    if terminal {
+        reopen_terminal().map_err(|error| format!("/dev/tty: {}", error))?;
+    }
+
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(unix)]
+fn reopen_terminal() -> std::io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    if unsafe { libc::isatty(libc::STDIN_FILENO) } == 1 {
+        return Ok(());
+    }
+
+    let terminal = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")?;
+    if unsafe { libc::dup2(terminal.as_raw_fd(), libc::STDIN_FILENO) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn reopen_terminal() -> std::io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,548 @@
+use crate::layout::{Layout, Position, Row};
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+/// Where XKB looks for symbols files, included files are also searched for here.
+const XKB_SYMBOLS_DIR: &str = "/usr/share/X11/xkb/symbols";
+
+/// How deep includes may nest before the keymap is assumed to include itself.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Key name prefixes of the rows of the alphanumeric section and the range of key numbers
+/// used. Key numbers are the columns of `Position`, the key left of `1` being `TLDE`.
+const KEY_ROWS: [(Row, &str, usize, usize); 4] = [
+    (Row::Number, "AE", 1, 12),
+    (Row::Top, "AD", 1, 12),
+    (Row::Home, "AC", 1, 11),
+    (Row::Bottom, "AB", 1, 10),
+];
+
+/// Keysym names of the printable ASCII characters that aren't their own name.
+const KEYSYMS: &[(&str, char)] = &[
+    ("space", ' '),
+    ("exclam", '!'),
+    ("quotedbl", '"'),
+    ("numbersign", '#'),
+    ("dollar", '$'),
+    ("percent", '%'),
+    ("ampersand", '&'),
+    ("apostrophe", '\''),
+    ("quoteright", '\''),
+    ("parenleft", '('),
+    ("parenright", ')'),
+    ("asterisk", '*'),
+    ("plus", '+'),
+    ("comma", ','),
+    ("minus", '-'),
+    ("period", '.'),
+    ("slash", '/'),
+    ("colon", ':'),
+    ("semicolon", ';'),
+    ("less", '<'),
+    ("equal", '='),
+    ("greater", '>'),
+    ("question", '?'),
+    ("at", '@'),
+    ("bracketleft", '['),
+    ("backslash", '\\'),
+    ("bracketright", ']'),
+    ("asciicircum", '^'),
+    ("underscore", '_'),
+    ("grave", '`'),
+    ("quoteleft", '`'),
+    ("braceleft", '{'),
+    ("bar", '|'),
+    ("braceright", '}'),
+    ("asciitilde", '~'),
+];
+
+/// The first two levels of a key, `None` where the keymap has no printable character.
+type Levels = [Option<char>; 2];
+
+/// Builds a layout from the `variant` section of an XKB symbols file, or from its default
+/// section if no variant is given, the way `setxkbmap -layout FILE -variant VARIANT` would.
+///
+/// Only the first group is used and only its first two levels, the unshifted and shifted
+/// characters. Included sections are read from the directory of `path` or else from
+/// `/usr/share/X11/xkb/symbols`. Keys whose characters have no printable equivalent, like
+/// dead keys, are left out.
+pub fn load_xkb_layout(path: &Path, variant: Option<&str>) -> Result<Layout, String> {
+    let mut keys = BTreeMap::new();
+    let mut name = None;
+    let search_dirs = vec![
+        path.parent().map(Path::to_path_buf).unwrap_or_default(),
+        PathBuf::from(XKB_SYMBOLS_DIR),
+    ];
+
+    load_section(path, variant, &search_dirs, &mut keys, &mut name, 0)?;
+
+    let mut positions = Vec::new();
+    for (row, prefix, first, last) in KEY_ROWS.iter() {
+        for number in *first..=*last {
+            let key_name = format!("{}{:02}", prefix, number);
+            if let Some(levels) = keys.get(&key_name) {
+                push_key(&mut positions, *row, number, levels);
+            }
+        }
+    }
+    if let Some(levels) = keys.get("TLDE") {
+        push_key(&mut positions, Row::Number, 0, levels);
+    }
+    if let Some(levels) = keys.get("BKSL") {
+        push_key(&mut positions, Row::Top, 13, levels);
+    }
+
+    if positions.is_empty() {
+        return Err(format!(
+            "{}: no keys of the alphanumeric section are defined",
+            path.display()
+        ));
+    }
+
+    positions.sort_by_key(|(position, _, _)| *position);
+    let name = name.unwrap_or_else(|| match variant {
+        Some(variant) => format!("{}({})", path.display(), variant),
+        None => path.display().to_string(),
+    });
+
+    Ok(Layout::from_positions(&name, &positions))
+}
+
+fn push_key(positions: &mut Vec<(Position, char, char)>, row: Row, column: usize, levels: &Levels) {
+    if let [Some(unshifted), shifted] = levels {
+        let shifted = shifted.unwrap_or_else(|| unshifted.to_uppercase().next().unwrap());
+        positions.push((Position { row, column }, *unshifted, shifted));
+    }
+}
+
+/// Merges the keys and name of a section of the symbols file at `path` into `keys` and
+/// `name`, later definitions replacing earlier ones level by level.
+fn load_section(
+    path: &Path,
+    variant: Option<&str>,
+    search_dirs: &[PathBuf],
+    keys: &mut BTreeMap<String, Levels>,
+    name: &mut Option<String>,
+    depth: usize,
+) -> Result<(), String> {
+    if depth > MAX_INCLUDE_DEPTH {
+        return Err(format!("{}: includes nest too deep", path.display()));
+    }
+
+    let source = read_to_string(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+    let tokens = tokenize(&source).map_err(|error| format!("{}: {}", path.display(), error))?;
+    let body = find_section(&tokens, variant).ok_or_else(|| match variant {
+        Some(variant) => format!("{}: there is no variant \"{}\"", path.display(), variant),
+        None => format!("{}: there is no xkb_symbols section", path.display()),
+    })?;
+
+    let error = |message: &str| format!("{}: {}", path.display(), message);
+
+    for mut statement in body.split(|token| *token == Token::Punct(';')) {
+        // includes don't need a semicolon, so one may start any statement
+        while let [Token::Word(keyword), Token::Str(spec), rest @ ..] = statement {
+            if !is_include(keyword) {
+                break;
+            }
+            for (file, variant) in include_specs(spec) {
+                let file_path = search_dirs
+                    .iter()
+                    .map(|dir| dir.join(&file))
+                    .find(|file_path| file_path.is_file())
+                    .ok_or_else(|| error(&format!("can't find included \"{}\"", file)))?;
+                load_section(
+                    &file_path,
+                    variant.as_deref(),
+                    search_dirs,
+                    keys,
+                    name,
+                    depth + 1,
+                )?;
+            }
+            statement = rest;
+        }
+
+        match statement {
+            [Token::Word(keyword), group @ .., Token::Punct('='), Token::Str(value)]
+                if keyword == "name" && is_group1(group) =>
+            {
+                *name = Some(value.clone());
+            }
+            [Token::Word(keyword), Token::KeyName(key_name), block @ ..] if keyword == "key" => {
+                if let Some(symbols) = key_symbols(block) {
+                    let levels = keys.entry(key_name.clone()).or_insert([None, None]);
+                    for (level, symbol) in levels.iter_mut().zip(symbols.iter()) {
+                        if let Some(symbol) = keysym_char(symbol) {
+                            *level = Some(symbol);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether `tokens` is the `[Group1]` index of a field.
+fn is_group1(tokens: &[Token]) -> bool {
+    match tokens {
+        [Token::Punct('['), Token::Word(group), Token::Punct(']')] => {
+            group.eq_ignore_ascii_case("group1")
+        }
+        _ => false,
+    }
+}
+
+fn is_include(keyword: &str) -> bool {
+    matches!(keyword, "include" | "augment" | "override" | "replace")
+}
+
+/// Splits an include like `us(dvorak)+level3(ralt_switch)` into file and variant pairs.
+fn include_specs(spec: &str) -> Vec<(String, Option<String>)> {
+    spec.split(['+', '|'])
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            // a trailing :N picks the group the section goes into, only group 1 is used
+            let part = part.split(':').next().unwrap_or(part);
+            match part.find('(') {
+                Some(open) => (
+                    part[..open].to_string(),
+                    Some(part[open + 1..].trim_end_matches(')').to_string()),
+                ),
+                None => (part.to_string(), None),
+            }
+        })
+        .collect()
+}
+
+/// The tokens between the braces of the section called `variant`, or of the default section.
+fn find_section<'a>(tokens: &'a [Token], variant: Option<&str>) -> Option<&'a [Token]> {
+    let mut first = None;
+    let mut default = None;
+    let mut flags_start = 0;
+    let mut index = 0;
+
+    while index < tokens.len() {
+        match &tokens[index..] {
+            [Token::Word(keyword), Token::Str(section), Token::Punct('{'), ..]
+                if keyword == "xkb_symbols" =>
+            {
+                let body_start = index + 3;
+                let body_end = matching_brace(tokens, body_start)?;
+                let body = &tokens[body_start..body_end];
+
+                if variant == Some(section.as_str()) {
+                    return Some(body);
+                }
+                let is_default = tokens[flags_start..index]
+                    .iter()
+                    .any(|token| *token == Token::Word("default".to_string()));
+                if is_default && default.is_none() {
+                    default = Some(body);
+                }
+                first = first.or(Some(body));
+
+                index = body_end + 1;
+                flags_start = index;
+            }
+            [Token::Punct(';'), ..] => {
+                index += 1;
+                flags_start = index;
+            }
+            _ => index += 1,
+        }
+    }
+
+    match variant {
+        Some(_) => None,
+        None => default.or(first),
+    }
+}
+
+/// The index of the `}` closing the block that starts at `start`.
+fn matching_brace(tokens: &[Token], start: usize) -> Option<usize> {
+    let mut depth = 1;
+    for (index, token) in tokens.iter().enumerate().skip(start) {
+        match token {
+            Token::Punct('{') => depth += 1,
+            Token::Punct('}') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The keysyms of the first group in the `{ ... }` block of a `key <NAME>` statement, either
+/// a bare `[ a, A ]` list or `symbols[Group1] = [ a, A ]`.
+fn key_symbols(block: &[Token]) -> Option<Vec<String>> {
+    let body = match block {
+        [Token::Punct('{'), body @ .., Token::Punct('}')] => body,
+        _ => return None,
+    };
+    let mut index = 0;
+
+    while index < body.len() {
+        let is_symbols = match &body[index..] {
+            [Token::Punct('['), ..] => true,
+            [Token::Word(keyword), rest @ ..] if keyword == "symbols" => {
+                let group_len = if rest.len() >= 3 && is_group1(&rest[..3]) {
+                    3
+                } else {
+                    0
+                };
+                match rest[group_len..] {
+                    [Token::Punct('='), Token::Punct('['), ..] => {
+                        index += group_len + 2;
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        };
+
+        if is_symbols {
+            let symbols = body[index + 1..]
+                .iter()
+                .take_while(|token| **token != Token::Punct(']'))
+                .filter_map(|token| match token {
+                    Token::Word(symbol) => Some(symbol.clone()),
+                    _ => None,
+                })
+                .collect();
+            return Some(symbols);
+        }
+
+        // skip to the next item of the key, past any list that isn't the symbols
+        let mut depth = 0;
+        while index < body.len() {
+            match body[index] {
+                Token::Punct('[') => depth += 1,
+                Token::Punct(']') => depth -= 1,
+                Token::Punct(',') if depth == 0 => break,
+                _ => {}
+            }
+            index += 1;
+        }
+        index += 1;
+    }
+
+    None
+}
+
+/// The character a keysym name types, if it's a printable one.
+fn keysym_char(keysym: &str) -> Option<char> {
+    let mut chars = keysym.chars();
+    if let (Some(character), None) = (chars.next(), chars.next()) {
+        return Some(character);
+    }
+
+    if let Some((_, character)) = KEYSYMS.iter().find(|(name, _)| *name == keysym) {
+        return Some(*character);
+    }
+
+    // Unicode keysyms like U20AC
+    if keysym.len() > 1 && keysym.starts_with('U') {
+        let character = u32::from_str_radix(&keysym[1..], 16)
+            .ok()
+            .and_then(std::char::from_u32)?;
+        if !character.is_control() {
+            return Some(character);
+        }
+    }
+
+    None
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    KeyName(String),
+    Punct(char),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    let mut line = 1;
+
+    while let Some(character) = chars.next() {
+        match character {
+            '\n' => line += 1,
+            _ if character.is_whitespace() => {}
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().is_some_and(|next| *next != '\n') {
+                    chars.next();
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for next in chars.by_ref() {
+                    if next == '\n' {
+                        line += 1;
+                    }
+                    if previous == '*' && next == '/' {
+                        break;
+                    }
+                    previous = next;
+                }
+            }
+            '"' => {
+                let value: String = chars.by_ref().take_while(|next| *next != '"').collect();
+                line += value.matches('\n').count();
+                tokens.push(Token::Str(value));
+            }
+            '<' => {
+                let value: String = chars.by_ref().take_while(|next| *next != '>').collect();
+                tokens.push(Token::KeyName(value));
+            }
+            _ if character.is_alphanumeric() || character == '_' => {
+                let mut word = character.to_string();
+                while let Some(next) = chars.peek() {
+                    if next.is_alphanumeric() || *next == '_' || *next == '.' {
+                        word.push(*next);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Word(word));
+            }
+            '{' | '}' | '[' | ']' | '(' | ')' | '=' | ',' | ';' | '+' | '-' | '!' => {
+                tokens.push(Token::Punct(character))
+            }
+            _ => return Err(format!("line {}: unexpected \"{}\"", line, character)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const BASE: &str = r#"
+// the first section isn't the default one
+partial alphanumeric_keys
+xkb_symbols "first" {
+    key <AD01> { [ z, Z ] };
+};
+
+default partial alphanumeric_keys
+xkb_symbols "basic" {
+    name[Group1] = "Base";
+    key <TLDE> { [ grave, asciitilde ] };
+    key <AE01> { [ 1, exclam ] };
+    key <AD01> { [ q, Q ] };
+    key <AC01> { [ a ] };
+    /* dead keys have no printable character */
+    key <AB01> { [ dead_acute, dead_grave ] };
+};
+
+partial alphanumeric_keys
+xkb_symbols "alt" {
+    include "base(basic)"
+    name[Group1] = "Base alt";
+    key <AD01> {
+        type[Group1] = "TWO_LEVEL",
+        symbols[Group1] = [ apostrophe, quotedbl ]
+    };
+    key <AC01> { [ U20AC, at ] };
+};
+"#;
+
+    /// A directory with the symbols file `base` in it.
+    fn symbols_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tutor-xkb-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("base"), BASE).unwrap();
+        dir
+    }
+
+    fn characters(layout: &Layout, row: Row, column: usize) -> Option<(char, char)> {
+        layout
+            .key_at(Position { row, column })
+            .map(|key| (key.unshifted, key.shifted))
+    }
+
+    #[test]
+    fn loads_the_default_section() {
+        let dir = symbols_dir("default");
+        let layout = load_xkb_layout(&dir.join("base"), None).unwrap();
+
+        assert_eq!(layout.name(), "Base");
+        assert_eq!(characters(&layout, Row::Number, 0), Some(('`', '~')));
+        assert_eq!(characters(&layout, Row::Number, 1), Some(('1', '!')));
+        assert_eq!(characters(&layout, Row::Top, 1), Some(('q', 'Q')));
+        // a missing shifted level is the upper case
+        assert_eq!(characters(&layout, Row::Home, 1), Some(('a', 'A')));
+        assert_eq!(characters(&layout, Row::Bottom, 1), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn loads_a_variant_on_top_of_its_includes() {
+        let dir = symbols_dir("variant");
+        let layout = load_xkb_layout(&dir.join("base"), Some("alt")).unwrap();
+
+        assert_eq!(layout.name(), "Base alt");
+        assert_eq!(characters(&layout, Row::Number, 1), Some(('1', '!')));
+        assert_eq!(characters(&layout, Row::Top, 1), Some(('\'', '"')));
+        assert_eq!(characters(&layout, Row::Home, 1), Some(('€', '@')));
+
+        assert!(load_xkb_layout(&dir.join("base"), Some("missing")).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reports_missing_includes_and_include_loops() {
+        let dir = symbols_dir("includes");
+        fs::write(
+            dir.join("broken"),
+            "xkb_symbols \"basic\" { include \"nowhere(basic)\" };",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("loop"),
+            "xkb_symbols \"basic\" { include \"loop(basic)\" };",
+        )
+        .unwrap();
+
+        let error = load_xkb_layout(&dir.join("broken"), None).unwrap_err();
+        assert!(
+            error.contains("can't find included \"nowhere\""),
+            "{}",
+            error
+        );
+        let error = load_xkb_layout(&dir.join("loop"), None).unwrap_err();
+        assert!(error.contains("nest too deep"), "{}", error);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn splits_include_specs() {
+        assert_eq!(
+            include_specs("us(dvorak)+level3(ralt_switch):2|inet"),
+            vec![
+                ("us".to_string(), Some("dvorak".to_string())),
+                ("level3".to_string(), Some("ralt_switch".to_string())),
+                ("inet".to_string(), None),
+            ]
+        );
+    }
+}
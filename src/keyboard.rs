@@ -0,0 +1,203 @@
+use crate::layout::{Finger, Layout, Row};
+
+/// How a cell of the keyboard diagram should stand out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Highlight {
+    None,
+    /// The key to press next.
+    Target,
+    /// Another key of the finger that presses the target.
+    Finger,
+    /// The shift key to hold down with the other hand.
+    Shift,
+}
+
+/// A labelled cell of the keyboard diagram.
+#[derive(Debug, Clone)]
+pub struct Cell {
+    pub label: String,
+    pub highlight: Highlight,
+}
+
+/// How many terminal rows the diagram takes, including the hint line.
+pub const KEYBOARD_HEIGHT: u16 = 6;
+
+const ROWS: [(Row, &str, &str); 5] = [
+    (Row::Number, "", ""),
+    (Row::Top, "Tab ", ""),
+    (Row::Home, "Caps ", ""),
+    (Row::Bottom, "Shift  ", "  Shift"),
+    (Row::Space, "", ""),
+];
+
+/// Lays out `layout` as rows of cells, highlighting the key for `target` and the finger
+/// that presses it. The shifted layer is shown when `target` needs shift.
+pub fn diagram(layout: &Layout, target: Option<char>) -> Vec<Vec<Cell>> {
+    let target_key = target.and_then(|target| layout.key_for(target));
+    let shifted = target.is_some_and(|target| layout.needs_shift(target));
+    let finger = target_key.map(|key| key.finger);
+
+    let mut rows = Vec::new();
+
+    for (row, left, right) in ROWS.iter() {
+        let mut cells = Vec::new();
+
+        if !left.is_empty() {
+            let shift_key = *row == Row::Bottom && shifted && finger.is_some_and(is_right_hand);
+            cells.push(label_cell(left, shift_key));
+        }
+
+        let mut keys: Vec<_> = layout
+            .keys()
+            .iter()
+            .filter(|key| key.row() == *row)
+            .collect();
+        keys.sort_by_key(|key| key.position.column);
+
+        for key in keys {
+            let highlight = if Some(key) == target_key {
+                Highlight::Target
+            } else if Some(key.finger) == finger {
+                Highlight::Finger
+            } else {
+                Highlight::None
+            };
+
+            let label = if *row == Row::Space {
+                format!("{:^31}", "space")
+            } else if shifted {
+                format!(" {} ", key.shifted)
+            } else {
+                format!(" {} ", key.unshifted)
+            };
+
+            if *row == Row::Space {
+                cells.push(label_cell("             ", false));
+            }
+            cells.push(Cell { label, highlight });
+        }
+
+        if !right.is_empty() {
+            let shift_key = *row == Row::Bottom && shifted && finger.is_some_and(is_left_hand);
+            cells.push(label_cell(right, shift_key));
+        }
+
+        rows.push(cells);
+    }
+
+    rows
+}
+
+/// A line describing how to type `target`, like "Next: { - left middle finger".
+pub fn hint(layout: &Layout, target: Option<char>) -> String {
+    let target = match target {
+        Some(target) => target,
+        None => return String::new(),
+    };
+
+    let name = if target == ' ' {
+        "space".to_string()
+    } else {
+        target.to_string()
+    };
+
+    match layout.key_for(target) {
+        Some(key) if layout.needs_shift(target) => {
+            let shift_hand = if is_left_hand(key.finger) {
+                "right"
+            } else {
+                "left"
+            };
+            format!(
+                "Next: {} - {} finger, holding {} shift",
+                name, key.finger, shift_hand
+            )
+        }
+        Some(key) => format!("Next: {} - {} finger", name, key.finger),
+        None => format!("Next: {}", name),
+    }
+}
+
+fn label_cell(label: &str, highlighted: bool) -> Cell {
+    Cell {
+        label: label.to_string(),
+        highlight: if highlighted {
+            Highlight::Shift
+        } else {
+            Highlight::None
+        },
+    }
+}
+
+fn is_left_hand(finger: Finger) -> bool {
+    finger < Finger::Thumb
+}
+
+fn is_right_hand(finger: Finger) -> bool {
+    finger > Finger::Thumb
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::PROGRAMMER_DVORAK;
+
+    /// The labels of the cells with `highlight`, trimmed.
+    fn highlighted(diagram: &[Vec<Cell>], highlight: Highlight) -> Vec<&str> {
+        diagram
+            .iter()
+            .flatten()
+            .filter(|cell| cell.highlight == highlight)
+            .map(|cell| cell.label.trim())
+            .collect()
+    }
+
+    #[test]
+    fn highlights_the_target_key_and_its_finger() {
+        let diagram = diagram(&PROGRAMMER_DVORAK, Some('u'));
+
+        assert_eq!(highlighted(&diagram, Highlight::Target), vec!["u"]);
+        assert_eq!(
+            highlighted(&diagram, Highlight::Finger),
+            vec!["}", "(", "p", "y", "i", "k", "x"]
+        );
+        assert!(highlighted(&diagram, Highlight::Shift).is_empty());
+
+        let diagram = super::diagram(&PROGRAMMER_DVORAK, None);
+        assert!(diagram
+            .iter()
+            .flatten()
+            .all(|cell| cell.highlight == Highlight::None));
+    }
+
+    #[test]
+    fn shows_the_shifted_layer_and_the_shift_key_of_the_other_hand() {
+        let diagram = diagram(&PROGRAMMER_DVORAK, Some('U'));
+
+        assert_eq!(highlighted(&diagram, Highlight::Target), vec!["U"]);
+        assert_eq!(highlighted(&diagram, Highlight::Shift), vec!["Shift"]);
+        let bottom = &diagram[3];
+        assert_eq!(bottom.last().unwrap().highlight, Highlight::Shift);
+
+        let diagram = super::diagram(&PROGRAMMER_DVORAK, Some('L'));
+        assert_eq!(diagram[3][0].highlight, Highlight::Shift);
+    }
+
+    #[test]
+    fn hints_say_which_finger_types_the_next_key() {
+        assert_eq!(
+            hint(&PROGRAMMER_DVORAK, Some('u')),
+            "Next: u - left index finger"
+        );
+        assert_eq!(
+            hint(&PROGRAMMER_DVORAK, Some('U')),
+            "Next: U - left index finger, holding right shift"
+        );
+        assert_eq!(
+            hint(&PROGRAMMER_DVORAK, Some('L')),
+            "Next: L - right pinky finger, holding left shift"
+        );
+        assert_eq!(hint(&PROGRAMMER_DVORAK, Some('é')), "Next: é");
+        assert_eq!(hint(&PROGRAMMER_DVORAK, None), "");
+    }
+}
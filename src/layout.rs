@@ -0,0 +1,463 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// The finger responsible for a key when touch typing.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Finger {
+    LeftPinky,
+    LeftRing,
+    LeftMiddle,
+    LeftIndex,
+    Thumb,
+    RightIndex,
+    RightMiddle,
+    RightRing,
+    RightPinky,
+}
+
+/// A row of the keyboard, from the number row down to the space bar.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Row {
+    Number,
+    Top,
+    Home,
+    Bottom,
+    Space,
+}
+
+impl fmt::Display for Finger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Finger::LeftPinky => "left pinky",
+            Finger::LeftRing => "left ring",
+            Finger::LeftMiddle => "left middle",
+            Finger::LeftIndex => "left index",
+            Finger::Thumb => "thumb",
+            Finger::RightIndex => "right index",
+            Finger::RightMiddle => "right middle",
+            Finger::RightRing => "right ring",
+            Finger::RightPinky => "right pinky",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for Row {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Row::Number => "number row",
+            Row::Top => "top row",
+            Row::Home => "home row",
+            Row::Bottom => "bottom row",
+            Row::Space => "space bar",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+use Finger::*;
+
+/// Fingers for each column of the number, top, home and bottom rows.
+const COLUMN_FINGERS: [Finger; 13] = [
+    LeftPinky,
+    LeftPinky,
+    LeftRing,
+    LeftMiddle,
+    LeftIndex,
+    LeftIndex,
+    RightIndex,
+    RightIndex,
+    RightMiddle,
+    RightRing,
+    RightPinky,
+    RightPinky,
+    RightPinky,
+];
+
+lazy_static! {
+    /// The built in Programmer Dvorak layout.
+    pub static ref PROGRAMMER_DVORAK: Layout = include_str!("../layouts/programmer-dvorak.layout")
+        .parse()
+        .unwrap();
+
+    /// US QWERTY, used to emulate other layouts on keyboards set up for QWERTY.
+    pub static ref QWERTY: Layout = include_str!("../layouts/qwerty.layout").parse().unwrap();
+}
+
+/// The layout definitions shipped with the tutor, by file name without extension.
+pub const BUILTIN_LAYOUTS: &[(&str, &str)] = &[
+    (
+        "programmer-dvorak",
+        include_str!("../layouts/programmer-dvorak.layout"),
+    ),
+    ("dvorak", include_str!("../layouts/dvorak.layout")),
+    ("qwerty", include_str!("../layouts/qwerty.layout")),
+    ("colemak", include_str!("../layouts/colemak.layout")),
+    ("colemak-dh", include_str!("../layouts/colemak-dh.layout")),
+    ("workman", include_str!("../layouts/workman.layout")),
+];
+
+/// Where a key physically sits on the keyboard.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub row: Row,
+    /// Column on a staggered ANSI keyboard, the key left of `1` on the number row is column
+    /// 0 and the first letter of the other rows is column 1.
+    pub column: usize,
+}
+
+/// A single key and the characters it types.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Key {
+    pub position: Position,
+    pub unshifted: char,
+    pub shifted: char,
+    pub finger: Finger,
+}
+
+impl Key {
+    pub fn row(&self) -> Row {
+        self.position.row
+    }
+}
+
+/// A keyboard layout, every key position with its characters and the finger that types it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Layout {
+    name: String,
+    keys: Vec<Key>,
+}
+
+impl Layout {
+    pub fn new(name: String, keys: Vec<Key>) -> Layout {
+        Layout { name, keys }
+    }
+
+    /// Builds a layout from (unshifted, shifted) pairs for each row, assigning fingers by
+    /// column the way touch typing is usually taught. A space bar typed with the thumbs is
+    /// added as well.
+    pub fn from_rows(name: &str, rows: &[(Row, Vec<(char, char)>)]) -> Layout {
+        let mut keys = Vec::new();
+
+        for (row, characters) in rows {
+            // only the number row starts left of the first letter column
+            let offset = if *row == Row::Number { 0 } else { 1 };
+
+            for (index, (unshifted, shifted)) in characters.iter().enumerate() {
+                let position = Position {
+                    row: *row,
+                    column: index + offset,
+                };
+                keys.push((position, *unshifted, *shifted));
+            }
+        }
+
+        Layout::from_positions(name, &keys)
+    }
+
+    /// Builds a layout from the (unshifted, shifted) pair at each position, assigning fingers
+    /// like `from_rows` does and adding the space bar.
+    pub fn from_positions(name: &str, positions: &[(Position, char, char)]) -> Layout {
+        let mut keys: Vec<Key> = positions
+            .iter()
+            .map(|(position, unshifted, shifted)| Key {
+                position: *position,
+                unshifted: *unshifted,
+                shifted: *shifted,
+                finger: column_finger(position.column),
+            })
+            .collect();
+
+        keys.push(Key {
+            position: Position {
+                row: Row::Space,
+                column: 0,
+            },
+            unshifted: ' ',
+            shifted: ' ',
+            finger: Thumb,
+        });
+
+        Layout::new(name.to_string(), keys)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether `name` refers to this layout, ignoring case, spaces and dashes.
+    pub fn is_named(&self, name: &str) -> bool {
+        names_match(&self.name, name)
+    }
+
+    pub fn keys(&self) -> &[Key] {
+        &self.keys
+    }
+
+    /// The key that types `character`, shifted or not.
+    pub fn key_for(&self, character: char) -> Option<&Key> {
+        self.keys
+            .iter()
+            .find(|key| key.unshifted == character || key.shifted == character)
+    }
+
+    pub fn key_at(&self, position: Position) -> Option<&Key> {
+        self.keys.iter().find(|key| key.position == position)
+    }
+
+    /// Which finger types `character`, for example the left middle finger for `{`.
+    pub fn finger(&self, character: char) -> Option<Finger> {
+        self.key_for(character).map(|key| key.finger)
+    }
+
+    pub fn row(&self, character: char) -> Option<Row> {
+        self.key_for(character).map(|key| key.row())
+    }
+
+    /// The character this layout types with the key that types `character` on `physical`,
+    /// shifted the same way. Characters `physical` can't type are returned unchanged.
+    ///
+    /// With `physical` set to `QWERTY` this turns what a QWERTY keyboard sends into what
+    /// the same keystrokes would type on this layout.
+    pub fn translate(&self, character: char, physical: &Layout) -> char {
+        let physical_key = match physical.key_for(character) {
+            Some(key) => key,
+            None => return character,
+        };
+
+        match self.key_at(physical_key.position) {
+            Some(key) if physical_key.unshifted == character => key.unshifted,
+            Some(key) => key.shifted,
+            None => character,
+        }
+    }
+
+    /// Whether `character` is on the shifted layer of its key.
+    pub fn needs_shift(&self, character: char) -> bool {
+        match self.key_for(character) {
+            Some(key) => key.unshifted != character,
+            None => false,
+        }
+    }
+}
+
+/// The finger for a column of the number, top, home and bottom rows.
+fn column_finger(column: usize) -> Finger {
+    COLUMN_FINGERS[column.min(COLUMN_FINGERS.len() - 1)]
+}
+
+impl FromStr for Finger {
+    type Err = String;
+
+    /// Parses names like "left index", "left-index" or "LeftIndex".
+    fn from_str(name: &str) -> Result<Finger, String> {
+        let normalized: String = name
+            .chars()
+            .filter(|character| character.is_alphanumeric())
+            .collect::<String>()
+            .to_lowercase();
+
+        match normalized.as_str() {
+            "leftpinky" => Ok(LeftPinky),
+            "leftring" => Ok(LeftRing),
+            "leftmiddle" => Ok(LeftMiddle),
+            "leftindex" => Ok(LeftIndex),
+            "thumb" | "thumbs" => Ok(Thumb),
+            "rightindex" => Ok(RightIndex),
+            "rightmiddle" => Ok(RightMiddle),
+            "rightring" => Ok(RightRing),
+            "rightpinky" => Ok(RightPinky),
+            _ => Err(format!("unknown finger \"{}\"", name)),
+        }
+    }
+}
+
+impl FromStr for Row {
+    type Err = String;
+
+    /// Parses names like "home", "home row" or "Home".
+    fn from_str(name: &str) -> Result<Row, String> {
+        let normalized = name.trim().to_lowercase();
+
+        match normalized.trim_end_matches(" row").trim_end_matches("row") {
+            "number" => Ok(Row::Number),
+            "top" | "upper" => Ok(Row::Top),
+            "home" => Ok(Row::Home),
+            "bottom" | "lower" => Ok(Row::Bottom),
+            "space" | "space bar" => Ok(Row::Space),
+            _ => Err(format!("unknown row \"{}\"", name)),
+        }
+    }
+}
+
+/// Parses a layout definition file.
+///
+/// ```text
+/// # comments start with a hash
+/// name: Colemak
+/// number: `~ 1! 2@ 3# 4$ 5% 6^ 7& 8* 9( 0) -_ =+
+/// top: q w f p g j l u y ;: [{ ]} \|
+/// home: a r s t d h n e i o '"
+/// bottom: z x c v b k m ,< .> /?
+/// ```
+///
+/// Each row lists its keys from left to right, a key being its unshifted character followed
+/// by its shifted one. A single letter stands for itself and its upper case. Fingers are
+/// assigned by column like on a staggered ANSI keyboard.
+impl FromStr for Layout {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Layout, String> {
+        let mut name = None;
+        let mut rows: Vec<(Row, Vec<(char, char)>)> = Vec::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = match line.find(':') {
+                Some(colon) => (line[..colon].trim(), line[colon + 1..].trim()),
+                None => return Err(format!("line {}: expected \"key: value\"", line_number)),
+            };
+
+            if key == "name" {
+                name = Some(value.to_string());
+                continue;
+            }
+
+            let row = key
+                .parse::<Row>()
+                .map_err(|error| format!("line {}: {}", line_number, error))?;
+            if row == Row::Space {
+                return Err(format!(
+                    "line {}: the space bar can't be redefined",
+                    line_number
+                ));
+            }
+            if rows.iter().any(|(existing, _)| *existing == row) {
+                return Err(format!("line {}: {} is defined twice", line_number, row));
+            }
+
+            let mut keys = Vec::new();
+            for token in value.split_whitespace() {
+                let mut chars = token.chars();
+                let key = match (chars.next(), chars.next(), chars.next()) {
+                    (Some(letter), None, None) if letter.is_alphabetic() => {
+                        (letter, letter.to_uppercase().next().unwrap_or(letter))
+                    }
+                    (Some(unshifted), Some(shifted), None) => (unshifted, shifted),
+                    _ => {
+                        return Err(format!(
+                            "line {}: \"{}\" should be an unshifted and a shifted character",
+                            line_number, token
+                        ))
+                    }
+                };
+                keys.push(key);
+            }
+
+            rows.push((row, keys));
+        }
+
+        let name = name.ok_or_else(|| "the layout has no name".to_string())?;
+        rows.sort_by_key(|(row, _)| *row);
+
+        Ok(Layout::from_rows(&name, &rows))
+    }
+}
+
+/// Whether two layout names are the same, ignoring case, spaces and dashes.
+pub fn names_match(a: &str, b: &str) -> bool {
+    normalize_name(a) == normalize_name(b)
+}
+
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|character| character.is_alphanumeric())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_layout_definitions() {
+        let layout: Layout = "# a comment\nname: Tiny\nhome: a s ;:\nnumber: `~ 1!\n"
+            .parse()
+            .unwrap();
+
+        assert_eq!(layout.name(), "Tiny");
+        // letters stand for themselves and their upper case
+        assert_eq!(layout.key_for('S').unwrap().unshifted, 's');
+        assert_eq!(layout.finger(':'), Some(LeftMiddle));
+        assert_eq!(layout.row('`'), Some(Row::Number));
+        assert_eq!(
+            layout.key_for('1').unwrap().position,
+            Position {
+                row: Row::Number,
+                column: 1
+            }
+        );
+        assert_eq!(layout.finger(' '), Some(Thumb));
+    }
+
+    #[test]
+    fn rejects_malformed_layout_definitions() {
+        let error = |source: &str| source.parse::<Layout>().unwrap_err();
+
+        assert_eq!(error("home: a s"), "the layout has no name");
+        assert_eq!(
+            error("name: X\nhome a s"),
+            "line 2: expected \"key: value\""
+        );
+        assert_eq!(
+            error("name: X\nmiddle: a"),
+            "line 2: unknown row \"middle\""
+        );
+        assert_eq!(
+            error("name: X\nhome: a\nhome: s"),
+            "line 3: home row is defined twice"
+        );
+        assert_eq!(
+            error("name: X\nspace: _"),
+            "line 2: the space bar can't be redefined"
+        );
+        assert_eq!(
+            error("name: X\ntop: abc"),
+            "line 2: \"abc\" should be an unshifted and a shifted character"
+        );
+    }
+
+    #[test]
+    fn every_builtin_layout_parses() {
+        for (file_name, source) in BUILTIN_LAYOUTS {
+            let layout = source.parse::<Layout>();
+            assert!(layout.is_ok(), "{}: {:?}", file_name, layout.err());
+        }
+    }
+
+    #[test]
+    fn translates_qwerty_keystrokes_to_programmer_dvorak() {
+        let translate = |typed: &str| -> String {
+            typed
+                .chars()
+                .map(|character| PROGRAMMER_DVORAK.translate(character, &QWERTY))
+                .collect()
+        };
+
+        assert_eq!(translate("`1234567890-="), "$&[{}(=*)+]!#");
+        assert_eq!(translate("~!@#$%^&*()_+"), "~%7531902468`");
+        assert_eq!(translate("qwerty asdf ;'"), ";,.pyf aoeu s-");
+        assert_eq!(translate("QWERTY ASDF :\""), ":<>PYF AOEU S_");
+        // characters QWERTY can't type pass through unchanged
+        assert_eq!(translate("é\t€"), "é\t€");
+    }
+}
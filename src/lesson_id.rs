@@ -0,0 +1,160 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// Separates the parts of a hierarchical id, like `unit3/04`.
+pub const SEPARATOR: char = '/';
+
+/// The id of a lesson: a number like `12`, a number with a suffix like `12b`, or a path of
+/// such parts like `unit3/04` for a lesson in a subdirectory.
+///
+/// Ids sort naturally, part by part. Runs of digits are compared by their value, so `9` comes
+/// before `10`, and an id comes before the longer ids starting with it, so `12` comes before
+/// `12b` and `unit3` before `unit3/04`. Numbers come before letters.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LessonId {
+    id: String,
+    /// The parts between separators, each split into runs of digits and of other characters.
+    parts: Vec<Vec<Chunk>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Chunk {
+    Number(u64),
+    Text(String),
+}
+
+impl LessonId {
+    pub fn as_str(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Ord for LessonId {
+    fn cmp(&self, other: &LessonId) -> Ordering {
+        // `05` and `5` sort the same way, their text tells them apart
+        self.parts
+            .cmp(&other.parts)
+            .then_with(|| self.id.cmp(&other.id))
+    }
+}
+
+impl PartialOrd for LessonId {
+    fn partial_cmp(&self, other: &LessonId) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for LessonId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+impl FromStr for LessonId {
+    type Err = String;
+
+    /// Parses ids made of letters, digits, `-` and `_`, in parts separated by `/`.
+    fn from_str(id: &str) -> Result<LessonId, String> {
+        if id.is_empty() {
+            return Err("the lesson id is empty".to_string());
+        }
+
+        let parts = id
+            .split(SEPARATOR)
+            .map(|part| parse_part(id, part))
+            .collect::<Result<_, _>>()?;
+
+        Ok(LessonId {
+            id: id.to_string(),
+            parts,
+        })
+    }
+}
+
+fn parse_part(id: &str, part: &str) -> Result<Vec<Chunk>, String> {
+    if part.is_empty() {
+        return Err(format!("lesson id \"{}\" has an empty part", id));
+    }
+    if let Some(character) = part
+        .chars()
+        .find(|&c| !c.is_ascii_alphanumeric() && c != '-' && c != '_')
+    {
+        return Err(format!(
+            "lesson id \"{}\" contains \"{}\", only letters, digits, - and _ are allowed",
+            id, character
+        ));
+    }
+
+    let mut chunks = Vec::new();
+    let mut rest = part;
+
+    while let Some(first) = rest.chars().next() {
+        let digits = first.is_ascii_digit();
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != digits)
+            .unwrap_or(rest.len());
+        let (chunk, tail) = rest.split_at(end);
+
+        chunks.push(if digits {
+            Chunk::Number(
+                chunk
+                    .parse()
+                    .map_err(|_| format!("the number in lesson id \"{}\" is too big", id))?,
+            )
+        } else {
+            Chunk::Text(chunk.to_string())
+        });
+        rest = tail;
+    }
+
+    Ok(chunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lesson_ids_sort_naturally_and_reject_malformed_names() {
+        let mut ids: Vec<LessonId> = [
+            "unit3/04",
+            "12b",
+            "unit3",
+            "10",
+            "9",
+            "12",
+            "unit10/01",
+            "05a",
+        ]
+        .iter()
+        .map(|id| id.parse().unwrap())
+        .collect();
+        ids.sort();
+        let ids: Vec<String> = ids.iter().map(LessonId::to_string).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "05a",
+                "9",
+                "10",
+                "12",
+                "12b",
+                "unit3",
+                "unit3/04",
+                "unit10/01"
+            ]
+        );
+
+        for malformed in &[
+            "",
+            "unit3/",
+            "/04",
+            "12 b",
+            "12.5",
+            "99999999999999999999999",
+        ] {
+            assert!(malformed.parse::<LessonId>().is_err(), "{:?}", malformed);
+        }
+    }
+}
@@ -1,234 +1,868 @@
 #[macro_use]
 extern crate clap;
 
+pub mod browser;
+pub mod code;
+pub mod course;
+pub mod drill;
+pub mod frontend;
+pub mod ghost;
 mod io;
-
-use crossterm::style::Colorize;
-use crossterm::terminal::size;
-use crossterm::{
-    cursor::{position, Hide, MoveDown, MoveLeft, MoveRight, MoveTo},
-    execute,
-    input::{input, AsyncReader, InputEvent, KeyEvent},
-    screen::{AlternateScreen, RawScreen},
-    style::{style, Color, PrintStyledContent},
-    terminal::{Clear, ClearType, ScrollDown, ScrollUp},
-    utils::Output,
-};
+pub mod keyboard;
+pub mod layout;
+pub mod lesson_id;
+pub mod lint;
+pub mod metrics;
+pub mod script;
+pub mod session;
+pub mod stats;
+pub mod terminal;
+pub mod text;
+
+use browser::LessonBrowser;
+use code::Language;
+use course::Course;
+use frontend::Frontend;
+use ghost::{Ghost, RaceAgainst};
 use indexmap::map::IndexMap;
+use layout::{Finger, Layout, Row};
 use lazy_static::lazy_static;
+use metrics::{PassCriteria, SessionMetrics};
+use script::ScriptedFrontend;
 use serde::Deserialize;
 use serde::Serialize;
-use std::cmp::Ordering::{Equal, Greater, Less};
-use std::io::{stdout, Stdout, Write};
-use walkdir::{DirEntry, WalkDir};
+use session::{LoggedEvent, TypingSession, Update};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use terminal::TerminalFrontend;
+
+pub use stats::Stats;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Lesson {
-    text: String,
     id: String,
+    title: String,
+    /// The practice lines, without the title.
+    text: String,
+    meta: LessonMeta,
+    /// Where the lesson was loaded from, `None` for built in and generated lessons.
+    #[serde(skip)]
+    file: Option<LessonFile>,
+}
+
+/// The file a lesson was loaded from, to point at its lines.
+#[derive(Clone, Debug)]
+pub struct LessonFile {
+    pub path: PathBuf,
+    /// The line of the file the practice text starts on, counting from 1.
+    pub text_line: usize,
+}
+
+/// Optional information declared in a lesson's front matter.
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+#[serde(default)]
+pub struct LessonMeta {
+    /// Keys this lesson introduces.
+    pub keys: Vec<char>,
+    /// Fingers that type the introduced keys.
+    pub fingers: Vec<Finger>,
+    /// Rows the introduced keys sit on.
+    pub rows: Vec<Row>,
+    /// Ids of lessons that should be finished first.
+    pub prerequisites: Vec<String>,
+    /// Net WPM needed to pass the lesson.
+    pub target_wpm: Option<f64>,
+    /// Accuracy in percent needed to pass the lesson.
+    pub target_accuracy: Option<f64>,
+    /// The most uncorrected errors allowed to pass the lesson.
+    pub max_errors: Option<u32>,
+    /// Seconds the lesson lasts from the first keystroke, if it's a timed test.
+    pub time_limit: Option<f64>,
+    pub description: Option<String>,
+    /// The layout the lesson is written for, Programmer Dvorak if not given.
+    pub layout: Option<String>,
+    /// The programming language of the practice text, for highlighting it.
+    pub language: Option<String>,
+    /// Type the indentation at the start of each line automatically.
+    pub auto_indent: bool,
 }
 
 impl Lesson {
-    fn new(text: String, id: String) -> Lesson {
-        Lesson { text, id }
+    pub fn new(id: String, title: String, text: String, meta: LessonMeta) -> Lesson {
+        Lesson {
+            id,
+            title,
+            text,
+            meta,
+            file: None,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn meta(&self) -> &LessonMeta {
+        &self.meta
+    }
+
+    pub fn file(&self) -> Option<&LessonFile> {
+        self.file.as_ref()
+    }
+
+    /// What a run of the lesson needs to pass, from its front matter.
+    pub fn pass_criteria(&self) -> PassCriteria {
+        PassCriteria {
+            min_accuracy: self.meta.target_accuracy,
+            min_net_wpm: self.meta.target_wpm,
+            max_uncorrected_errors: self.meta.max_errors,
+        }
+    }
+
+    /// A copy of the lesson with the pass criteria it doesn't set itself taken from
+    /// `defaults`.
+    pub fn with_default_criteria(&self, defaults: PassCriteria) -> Lesson {
+        let criteria = self.pass_criteria().or(defaults);
+        let mut lesson = self.clone();
+        lesson.meta.target_accuracy = criteria.min_accuracy;
+        lesson.meta.target_wpm = criteria.min_net_wpm;
+        lesson.meta.max_errors = criteria.max_uncorrected_errors;
+        lesson
     }
-}
 
-#[derive(Serialize, Deserialize)]
-pub struct Stats {
-    last_lesson_id: String,
+    /// The name of the layout the lesson is written for, Programmer Dvorak if it doesn't say.
+    pub fn layout(&self) -> &str {
+        self.meta
+            .layout
+            .as_deref()
+            .unwrap_or(layout::PROGRAMMER_DVORAK.name())
+    }
+
+    pub fn is_for_layout(&self, layout: &str) -> bool {
+        layout::names_match(self.layout(), layout)
+    }
 }
 
 /// An input (user) event.
-#[derive(Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
 pub enum Event {
     InputCharacter(char),
     NewLine,
+    #[serde(rename = "backspace")]
     BackSpace,
     Quit,
 }
 
 lazy_static! {
-    pub static ref LESSONS: IndexMap<String, Lesson> = io::lesson::load_lessons();
+    /// Every lesson from the lesson search path, ordered by id.
+    pub static ref LESSONS: IndexMap<String, Lesson> = {
+        let (lessons, errors) = io::lesson::load_lessons(
+            &LESSON_DIRS.lock().unwrap(),
+            Some(&LESSON_LAYOUT.lock().unwrap()),
+        );
+        for error in errors {
+            eprintln!("Skipping {}", error);
+        }
+        lessons
+    };
+
+    /// The lessons grouped into units, from the course manifest.
+    pub static ref COURSE: Course = {
+        // loading the lessons locks the directories too, so they go first
+        let lessons = &*LESSONS;
+        io::course::load_course(&LESSON_DIRS.lock().unwrap(), lessons)
+    };
+
+    /// Directories given with `--lesson-dir`, must be filled in before `LESSONS` is first used.
+    static ref LESSON_DIRS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+    /// The name of the layout given with `--layout`, `LESSONS` only holds lessons for it.
+    static ref LESSON_LAYOUT: Mutex<String> =
+        Mutex::new(layout::PROGRAMMER_DVORAK.name().to_string());
+}
+
+/// Why the command line tool stopped early.
+#[derive(Debug)]
+pub enum AppError {
+    /// Using the terminal or saving the results failed.
+    Io(crossterm::ErrorKind),
+    /// The command line can't be carried out, like when a file it names can't be loaded.
+    Usage(String),
+    /// `validate` found this many problems in the lessons.
+    Problems(usize),
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AppError::Io(error) => write!(f, "{}", error),
+            AppError::Usage(message) => write!(f, "{}", message),
+            AppError::Problems(count) => write!(
+                f,
+                "Found {} problem{} in the lessons",
+                count,
+                if *count == 1 { "" } else { "s" }
+            ),
+        }
+    }
+}
+
+impl From<crossterm::ErrorKind> for AppError {
+    fn from(error: crossterm::ErrorKind) -> AppError {
+        AppError::Io(error)
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(error: std::io::Error) -> AppError {
+        AppError::Io(error.into())
+    }
 }
 
-pub fn create_app() {
+impl From<String> for AppError {
+    fn from(message: String) -> AppError {
+        AppError::Usage(message)
+    }
+}
+
+pub fn create_app() -> Result<(), AppError> {
     let yaml = load_yaml!("../cli.yml");
     let matches = clap::App::from(yaml).get_matches();
 
-    if matches.is_present("list") {
-        for lesson in LESSONS.values() {
-            println!("{}", lesson.text.lines().next().unwrap())
+    if let Some(dirs) = matches.values_of("lesson-dir") {
+        LESSON_DIRS.lock().unwrap().extend(dirs.map(PathBuf::from));
+    }
+
+    let layout = match matches.value_of("layout") {
+        Some(name) => io::layout::load_layout(name)?,
+        None => layout::PROGRAMMER_DVORAK.clone(),
+    };
+    *LESSON_LAYOUT.lock().unwrap() = layout.name().to_string();
+
+    // the keymap replaces the keys of the layout being learned, the lessons stay the same
+    let layout = match matches.value_of("xkb") {
+        Some(path) => {
+            let variant = matches.value_of("xkb-variant");
+            io::xkb::load_xkb_layout(Path::new(path), variant)?
         }
+        None => layout,
+    };
+
+    if let Some(matches) = matches.subcommand_matches("validate") {
+        return validate_lessons(matches, &layout);
     }
 
-    if matches.is_present("lesson") {
-        let lesson_str = matches.value_of("lesson").unwrap();
+    let mut script = match matches.value_of("script") {
+        Some(path) => {
+            let events = io::script::load_script(Path::new(path))?;
+            Some(ScriptedFrontend::new(events))
+        }
+        None => None,
+    };
+
+    let race = if matches.is_present("race") {
+        match matches.value_of("race") {
+            Some("last") => Some(RaceAgainst::Last),
+            _ => Some(RaceAgainst::Best),
+        }
+    } else {
+        None
+    };
 
-        let mut next_lesson = lesson_str;
+    let pass_criteria = parse_pass_criteria(&matches)?;
 
-        let test: IndexMap<String, Lesson> = IndexMap::new();
-        test.get_index(1);
+    let mut stats = io::stats::load_stats();
 
-        loop {
-            if run_lesson(&LESSONS[next_lesson]) {
-                let index = LESSONS.get_full(next_lesson).unwrap().0;
-                next_lesson = LESSONS.get_index(index + 1).unwrap().0;
+    let options = LessonOptions {
+        show_keyboard: matches.is_present("keyboard"),
+        qwerty_emulation: matches.is_present("qwerty"),
+        layout,
+        log_keystrokes: matches.is_present("log-keystrokes") || race.is_some(),
+        race,
+        highlight_syntax: matches.is_present("highlight"),
+        pass_criteria,
+    };
+
+    // without anything to do, pick lessons in the browser
+    let has_action = ACTIONS.iter().any(|action| matches.is_present(action));
+    if !has_action && script.is_none() {
+        run_browser(&mut stats, &options)?;
+    }
+
+    if matches.is_present("list") {
+        for unit in COURSE.units() {
+            if unit.lessons.is_empty() && unit.review.is_empty() {
                 continue;
-            } else {
-                break;
             }
+
+            let locked = !COURSE.is_unit_unlocked(unit, &stats);
+            println!("{}{}", unit.title, if locked { " (locked)" } else { "" });
+
+            for id in &unit.lessons {
+                println!("  {}", LESSONS[id.as_str()].title);
+            }
+            for id in &unit.review {
+                println!("  {} (review)", LESSONS[id.as_str()].title);
+            }
+        }
+    }
+
+    if matches.is_present("lesson") {
+        let lesson_id = matches.value_of("lesson").unwrap();
+
+        if LESSONS.contains_key(lesson_id) {
+            let mut frontend = open_frontend(&mut script, &options)?;
+            run_lessons_from(&mut *frontend, lesson_id, &mut stats, &options)?;
+        } else {
+            return Err(format!("There is no lesson {}, see --list", lesson_id).into());
         }
     }
 
     if matches.is_present("continue") {
-        let stats = io::stats::load_stats();
+        // the first lesson of the course that is unlocked but wasn't passed yet
+        match COURSE.continue_lesson(&LESSONS, &stats) {
+            Some(next_lesson) => {
+                let mut frontend = open_frontend(&mut script, &options)?;
+                run_lessons_from(&mut *frontend, next_lesson, &mut stats, &options)?;
+            }
+            None => println!("All lessons finished, pick one with --lesson to practice again"),
+        }
     }
-}
 
-fn run_lesson(lesson: &Lesson) -> bool {
-    let alt = AlternateScreen::to_alternate(true);
+    if matches.is_present("adaptive") {
+        run_adaptive_drills(&mut script, &mut stats, &options)?;
+    }
 
-    #[allow(unused)]
-    let screen = RawScreen::into_raw_mode();
+    let text = if let Some(path) = matches.value_of("text") {
+        Some((path.to_string(), io::text::load_text(Path::new(path))))
+    } else if matches.is_present("stdin") {
+        // scripted runs don't need a terminal to read keys from
+        let text = io::text::read_stdin(script.is_none());
+        Some(("Standard input".to_string(), text))
+    } else {
+        None
+    };
+    if let Some((title, source)) = text {
+        run_text(&mut script, &mut stats, &options, &title, &source?)?;
+    }
 
-    let mut stdin = input().read_async();
-    let mut stdout = stdout();
+    if let Some(paths) = matches.values_of("code") {
+        let language = matches
+            .value_of("language")
+            .map(str::parse::<Language>)
+            .transpose()?;
 
-    execute!(&mut stdout, Hide);
+        let lessons = load_code_lessons(paths, language)?;
+        run_code(&mut script, &mut stats, &options, &lessons)?;
+    }
 
-    let mut lines = lesson.text.lines();
-    let title = lines.next().unwrap().to_string();
+    if let Some(seconds) = matches.value_of("timed") {
+        let seconds = match seconds.parse::<f64>() {
+            Ok(seconds) if seconds > 0.0 && seconds <= drill::MAX_TIMED_SECONDS => seconds,
+            _ => {
+                return Err(format!(
+                    "--timed takes a number of seconds up to {}, not {}",
+                    drill::MAX_TIMED_SECONDS,
+                    seconds
+                )
+                .into())
+            }
+        };
 
-    execute!(&mut stdout, MoveTo(0, 0), Output(title), MoveTo(0, 1));
+        let word_list = match matches.value_of("word-list") {
+            Some(path) => {
+                let word_list = std::fs::read_to_string(path)
+                    .map_err(|error| format!("{}: {}", path, error))?;
+                if word_list.split_whitespace().next().is_none() {
+                    return Err(format!("{}: the word list has no words", path).into());
+                }
+                Some(word_list)
+            }
+            None => None,
+        };
 
-    'outer: for line in lines {
-        // if at bottom of terminal, scroll up
-        let scroll = position().unwrap().1 >= size().unwrap().1 - 1;
+        run_timed_tests(
+            &mut script,
+            &mut stats,
+            &options,
+            seconds,
+            word_list.as_deref(),
+        )?;
+    }
 
-        if scroll {
-            execute!(&mut stdout, ScrollUp(1));
+    if matches.is_present("stats") {
+        for line in stats.report(&options.layout) {
+            println!("{}", line);
         }
+    }
 
-        execute!(
-            &mut stdout,
-            Output(line.to_string()),
-            MoveTo(0, position().unwrap().1 + 1)
-        );
+    Ok(())
+}
+
+/// The pass criteria given on the command line.
+fn parse_pass_criteria(matches: &clap::ArgMatches) -> Result<PassCriteria, String> {
+    fn number<T: FromStr>(matches: &clap::ArgMatches, name: &str) -> Result<Option<T>, String> {
+        matches
+            .value_of(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| format!("--{} takes a number, not {}", name, value))
+            })
+            .transpose()
+    }
+
+    Ok(PassCriteria {
+        min_accuracy: number(matches, "min-accuracy")?,
+        min_net_wpm: number(matches, "min-wpm")?,
+        max_uncorrected_errors: number(matches, "max-errors")?,
+    })
+}
 
-        if scroll {
-            execute!(&mut stdout, ScrollUp(1));
+/// Arguments that make the command line tool do something, without any of them it shows the
+/// lesson browser.
+const ACTIONS: &[&str] = &[
+    "lesson", "continue", "adaptive", "text", "stdin", "code", "timed", "list", "stats",
+];
+
+/// Loads the lessons like `LESSONS`, plus the ones in the directories given to `validate`, and
+/// prints every problem found in them. Returns `AppError::Problems` if there are any, which
+/// makes the command line tool exit with status 1, so lesson repositories can check their
+/// lessons with it.
+///
+/// Lessons for every layout are checked, each layout's lessons on their own and with their
+/// own layout, so keys are introduced per layout. `layout`, from `--layout` and `--xkb`, is
+/// used for its lessons and for lessons for layouts that can't be loaded.
+fn validate_lessons(matches: &clap::ArgMatches, layout: &Layout) -> Result<(), AppError> {
+    let width = match matches.value_of("width").map(str::parse::<usize>) {
+        Some(Ok(width)) => width,
+        Some(Err(_)) => return Err("--width takes a whole number of columns".to_string().into()),
+        None => 80,
+    };
+
+    let mut dirs = LESSON_DIRS.lock().unwrap().clone();
+    if let Some(extra_dirs) = matches.values_of("DIR") {
+        dirs.extend(extra_dirs.map(PathBuf::from));
+    }
+
+    let (lessons, errors) = io::lesson::load_lessons(&dirs, None);
+
+    let mut layout_names: Vec<&str> = Vec::new();
+    for lesson in lessons.values() {
+        if !layout_names
+            .iter()
+            .any(|name| layout::names_match(name, lesson.layout()))
+        {
+            layout_names.push(lesson.layout());
         }
+    }
 
-        let chars: Vec<char> = line.chars().collect();
+    let mut problems = Vec::new();
+    for name in layout_names {
+        let lesson_layout = if layout::names_match(name, &LESSON_LAYOUT.lock().unwrap()) {
+            layout.clone()
+        } else {
+            io::layout::load_layout(name).unwrap_or_else(|_| layout.clone())
+        };
 
-        let line_length = chars.len();
+        let mut layout_lessons = lessons.clone();
+        layout_lessons.retain(|_, lesson| lesson.is_for_layout(name));
+        problems.extend(lint::lint_lessons(&layout_lessons, &lesson_layout, width));
+    }
 
-        let mut char_index = 0;
+    for error in &errors {
+        println!("{}", error);
+    }
+    for problem in &problems {
+        println!("{}", problem);
+    }
 
-        'char: loop {
-            'input: loop {
-                match next_event(&mut stdin) {
-                    Some(Event::InputCharacter(character)) => {
-                        // if at end of line don't write character
-                        if char_index < line_length {
-                            write_character(&mut stdout, chars[char_index], character);
-                            break;
-                        }
-                    }
-                    Some(Event::NewLine) => {
-                        if char_index == line_length {
-                            execute!(&mut stdout, MoveDown(1), MoveTo(0, position().unwrap().1));
-                            break 'char;
-                        }
-                    }
-                    Some(Event::BackSpace) => {
-                        if position().unwrap().0 > 0 {
-                            execute!(&mut stdout, MoveLeft(1), Clear(ClearType::UntilNewLine));
-                            char_index -= 1;
-                        }
-                    }
-                    Some(Event::Quit) => break 'outer,
-                    _ => {}
-                }
+    let count = errors.len() + problems.len();
+    if count > 0 {
+        return Err(AppError::Problems(count));
+    }
+
+    println!("Checked {} lessons, no problems found", lessons.len());
+    Ok(())
+}
+
+/// Shows the lesson browser and runs the lessons picked in it, until the user quits.
+fn run_browser(stats: &mut Stats, options: &LessonOptions) -> crossterm::Result<()> {
+    let mut frontend = open_terminal(options)?;
+    let mut browser = LessonBrowser::new(&LESSONS, stats);
+    if let Some(lesson_id) = COURSE.continue_lesson(&LESSONS, stats) {
+        browser.select(lesson_id);
+    }
+
+    while let Some(lesson_id) = frontend.browse(&mut browser, &LESSONS)? {
+        run_lessons_from(&mut frontend, &lesson_id, stats, options)?;
+        browser.update_progress(stats);
+    }
+
+    Ok(())
+}
+
+/// The frontend the command line tool runs lessons in.
+type AppFrontend<'a> = dyn Frontend<Error = crossterm::ErrorKind> + 'a;
+
+/// Opens the frontend to run lessons in, the script given with `--script` if there is one
+/// and the terminal otherwise.
+fn open_frontend<'a>(
+    script: &'a mut Option<ScriptedFrontend>,
+    options: &'a LessonOptions,
+) -> crossterm::Result<Box<AppFrontend<'a>>> {
+    Ok(match script {
+        Some(script) => Box::new(script),
+        None => Box::new(open_terminal(options)?),
+    })
+}
+
+/// Switches the terminal to the alternate screen, after loading the lessons and the course so
+/// the errors found in them are printed where they can be read.
+fn open_terminal(options: &LessonOptions) -> crossterm::Result<TerminalFrontend<'_>> {
+    // the course loads the lessons first
+    lazy_static::initialize(&COURSE);
+    TerminalFrontend::new(options)
+}
+
+/// Runs lessons starting at `lesson_id` for as long as the user wants to continue, saving
+/// progress after each one. Lessons that aren't passed are tried again, passed ones are
+/// followed by the next unlocked lesson of the course.
+fn run_lessons_from(
+    frontend: &mut AppFrontend,
+    lesson_id: &str,
+    stats: &mut Stats,
+    options: &LessonOptions,
+) -> crossterm::Result<()> {
+    let mut lesson_id = lesson_id.to_string();
+
+    while let Some(lesson) = LESSONS.get(lesson_id.as_str()) {
+        let ghost = match options.race {
+            Some(against) => {
+                let runs = io::keystroke_log::load_runs(&lesson.id)?;
+                Ghost::choose(lesson, &runs, against)
+            }
+            None => None,
+        };
+
+        let lesson = lesson.with_default_criteria(options.pass_criteria);
+        let outcome = run_lesson(frontend, &lesson, stats, ghost.as_ref())?;
+        stats.record_lesson(
+            &lesson.id,
+            &outcome.metrics,
+            outcome.completed,
+            outcome.passed,
+        );
+        io::stats::save_stats(stats)?;
+        if options.log_keystrokes {
+            io::keystroke_log::append_log(&lesson.id, &outcome.events)?;
+        }
+
+        if !outcome.next {
+            break;
+        }
+        if outcome.passed {
+            match COURSE.next_lesson(&lesson.id, &LESSONS, stats) {
+                Some(next) => lesson_id = next.to_string(),
+                None => break,
             }
-            char_index += 1;
         }
     }
 
-    // Lesson finished
+    Ok(())
+}
+
+/// Runs a lesson that isn't part of the course, like a drill, a timed test or the user's own
+/// text, and saves its results. It only feeds the key statistics, it doesn't count as lesson
+/// progress.
+fn run_practice(
+    frontend: &mut AppFrontend,
+    lesson: &Lesson,
+    stats: &mut Stats,
+    options: &LessonOptions,
+) -> crossterm::Result<LessonOutcome> {
+    let outcome = run_lesson(frontend, lesson, stats, None)?;
+    io::stats::save_stats(stats)?;
+    if options.log_keystrokes {
+        io::keystroke_log::append_log(&lesson.id, &outcome.events)?;
+    }
+    Ok(outcome)
+}
 
-    // If at bottom scroll up
-    if position().unwrap().1 >= size().unwrap().1 - 1 {
-        execute!(&mut stdout, ScrollUp(1));
+/// Generates adaptive drills from the recorded statistics until the user stops.
+fn run_adaptive_drills(
+    script: &mut Option<ScriptedFrontend>,
+    stats: &mut Stats,
+    options: &LessonOptions,
+) -> crossterm::Result<()> {
+    let keys = drill::introduced_keys(&LESSONS, stats);
+
+    if keys.len() <= 1 {
+        println!(
+            "Finish a lesson first, adaptive drills only use keys you have been introduced to"
+        );
+        return Ok(());
     }
 
-    execute!(
-        &mut stdout,
-        MoveTo(0, position().unwrap().1 + 1),
-        Output("Lesson finished, next lesson? (y/n) ")
-    );
+    let mut frontend = open_frontend(script, options)?;
+
+    loop {
+        let lesson = drill::generate_drill(stats, &keys);
+        let outcome = run_practice(&mut *frontend, &lesson, stats, options)?;
+
+        if !outcome.next {
+            return Ok(());
+        }
+    }
+}
 
-    let start_x = position().unwrap().0;
+/// Practices arbitrary text for as long as the user wants to go again.
+fn run_text(
+    script: &mut Option<ScriptedFrontend>,
+    stats: &mut Stats,
+    options: &LessonOptions,
+    title: &str,
+    source: &str,
+) -> Result<(), AppError> {
+    // one column is left free so the cursor doesn't wrap at the end of a line
+    let width = crossterm::terminal::size()
+        .map(|(columns, _)| columns)
+        .unwrap_or(80);
+    let lesson = text::text_lesson(title, source, usize::from(width).saturating_sub(1));
+
+    if lesson.text().is_empty() {
+        return Err(format!("{}: there is no text to practice", title).into());
+    }
 
-    let mut answer = false;
+    let mut frontend = open_frontend(script, options)?;
 
     loop {
-        match next_event(&mut stdin) {
-            Some(Event::InputCharacter(mut character)) => {
-                character.make_ascii_lowercase();
-                if let 'y' | 'n' = character {
-                    if position().unwrap().0 > start_x {
-                        execute!(&mut stdout, MoveLeft(1), Clear(ClearType::UntilNewLine));
-                    }
-                    execute!(&mut stdout, Output(character));
+        let outcome = run_practice(&mut *frontend, &lesson, stats, options)?;
 
-                    if character == 'y' {
-                        answer = true;
-                    } else if character == 'n' {
-                        answer = false;
-                    }
-                }
-            }
+        if !outcome.next {
+            return Ok(());
+        }
+    }
+}
 
-            Some(Event::NewLine) => {
-                return answer;
-            }
+/// Makes lessons of the snippets of source files, in the language of their extension unless
+/// `language` is given.
+fn load_code_lessons<'a>(
+    paths: impl Iterator<Item = &'a str>,
+    language: Option<Language>,
+) -> Result<Vec<Lesson>, String> {
+    let width = crossterm::terminal::size()
+        .map(|(columns, _)| columns)
+        .unwrap_or(80);
+    let width = usize::from(width).saturating_sub(1);
+    let mut lessons = Vec::new();
+
+    for path in paths {
+        let language = language
+            .or_else(|| Language::from_path(Path::new(path)))
+            .ok_or_else(|| format!("{}: unknown language, pick one with --language", path))?;
+        let source = io::text::load_text(Path::new(path))?;
+
+        for snippet in code::snippets(language, &source, width) {
+            let title = format!("{}:{}", path, snippet.line);
+            lessons.push(code::code_lesson(language, title, &snippet));
+        }
+    }
 
-            Some(Event::Quit) => {
-                break;
-            }
+    if lessons.is_empty() {
+        return Err("There is no code to practice in these files".to_string());
+    }
+    Ok(lessons)
+}
+
+/// Practices snippets of code in order for as long as the user wants to continue.
+fn run_code(
+    script: &mut Option<ScriptedFrontend>,
+    stats: &mut Stats,
+    options: &LessonOptions,
+    lessons: &[Lesson],
+) -> crossterm::Result<()> {
+    let mut frontend = open_frontend(script, options)?;
+
+    for lesson in lessons {
+        let outcome = run_practice(&mut *frontend, lesson, stats, options)?;
 
-            _ => {}
+        if !outcome.next {
+            break;
         }
     }
 
-    return false;
+    Ok(())
 }
 
-fn next_event(reader: &mut AsyncReader) -> Option<Event> {
-    while let Some(event) = reader.next() {
-        match event {
-            InputEvent::Keyboard(KeyEvent::Char(character)) => {
-                return Some(Event::InputCharacter(character));
+/// Runs timed tests until the user stops, then prints the results of each.
+fn run_timed_tests(
+    script: &mut Option<ScriptedFrontend>,
+    stats: &mut Stats,
+    options: &LessonOptions,
+    seconds: f64,
+    word_list: Option<&str>,
+) -> crossterm::Result<()> {
+    let mut outcomes = Vec::new();
+
+    {
+        let mut frontend = open_frontend(script, options)?;
+
+        loop {
+            let lesson = drill::generate_timed_test(&LESSONS, word_list, seconds);
+            let outcome = run_practice(&mut *frontend, &lesson, stats, options)?;
+
+            let next = outcome.next;
+            outcomes.push(outcome);
+            if !next {
+                break;
             }
-            InputEvent::Keyboard(KeyEvent::Esc) => return Some(Event::Quit),
-            InputEvent::Keyboard(KeyEvent::Ctrl('c')) => return Some(Event::Quit),
+        }
+    }
 
-            InputEvent::Keyboard(KeyEvent::Backspace) => return Some(Event::BackSpace),
-            InputEvent::Keyboard(KeyEvent::Enter) => return Some(Event::NewLine),
-            _ => {}
+    // printed once the terminal is back from the alternate screen, so the results stay
+    for (index, outcome) in outcomes.iter().enumerate() {
+        // a test that ended before the first keystroke has no results
+        let end = match outcome.metrics.end() {
+            Some(end) if outcome.completed => end,
+            _ => continue,
         };
+
+        println!("Timed test {}:", index + 1);
+        for line in outcome.metrics.summary(end) {
+            println!("  {}", line);
+        }
+
+        let missed: Vec<String> = session::missed_characters(&outcome.events)
+            .into_iter()
+            .take(5)
+            .map(|(character, count)| format!("{:?} {}x", character, count))
+            .collect();
+        if !missed.is_empty() {
+            println!("  Most missed:        {}", missed.join(", "));
+        }
     }
-    None
+
+    Ok(())
+}
+
+/// Settings from the command line that change how lessons are run.
+pub struct LessonOptions {
+    /// Draw the keyboard below the practice text.
+    pub show_keyboard: bool,
+    /// Treat typed characters as QWERTY key positions and translate them to `layout`, for
+    /// machines without the layout installed.
+    pub qwerty_emulation: bool,
+    /// The layout being learned.
+    pub layout: Layout,
+    /// Append every event of every lesson to the keystroke log.
+    pub log_keystrokes: bool,
+    /// Race against the ghost of a logged run of each lesson.
+    pub race: Option<RaceAgainst>,
+    /// What lessons need to pass, unless they set their own criteria.
+    pub pass_criteria: PassCriteria,
+    /// Highlight the syntax of lessons in a programming language.
+    pub highlight_syntax: bool,
+}
+
+/// How a single run of `run_lesson` ended.
+pub struct LessonOutcome {
+    pub metrics: SessionMetrics,
+    /// Whether every line was typed, as opposed to quitting early.
+    pub completed: bool,
+    /// Whether the lesson was completed and met its pass criteria.
+    pub passed: bool,
+    /// Whether the user asked for the next lesson, or to try again if it wasn't passed.
+    pub next: bool,
+    /// Every event of the lesson, for the keystroke log.
+    pub events: Vec<LoggedEvent>,
 }
 
-fn write_character(stdout: &mut Stdout, current_char: char, input_char: char) {
-    let content = if current_char == input_char {
-        style(input_char).black().on_green()
+/// Runs `lesson` in `frontend`, recording every keystroke in `stats`. With a `ghost` the
+/// frontend is kept up to date on where that previous run would be.
+pub fn run_lesson<F: Frontend + ?Sized>(
+    frontend: &mut F,
+    lesson: &Lesson,
+    stats: &mut Stats,
+    ghost: Option<&Ghost>,
+) -> Result<LessonOutcome, F::Error> {
+    let mut session = TypingSession::new(lesson);
+
+    frontend.start_lesson(lesson)?;
+
+    while !session.is_finished() {
+        let line_index = session.line_index();
+
+        frontend.show_line(&session)?;
+        frontend.show_status(&session)?;
+        let mut ghost_position = None;
+        let mut shown_second = None;
+
+        while session.line_index() == line_index && !session.is_finished() {
+            let event = frontend.next_event()?;
+            let now = frontend.now();
+
+            if let Some(ghost) = ghost {
+                let position = ghost.position(session.metrics().elapsed(now));
+                if ghost_position != Some(position) {
+                    frontend.show_ghost(&session, position)?;
+                    ghost_position = Some(position);
+                }
+            }
+
+            let update = match event {
+                Some(event) => session.handle(event, now),
+                None => session.check_time(now),
+            };
+            match update {
+                Update::Typed(keystroke) => stats.record_keystroke(
+                    keystroke.previous,
+                    keystroke.expected,
+                    keystroke.typed,
+                    keystroke.latency,
+                ),
+                Update::Ignored | Update::Quit => {
+                    // keep the clock of timed lessons running while nothing is typed
+                    let second = session.metrics().elapsed(now).as_secs();
+                    if session.time_limit().is_some() && shown_second != Some(second) {
+                        frontend.show_status(&session)?;
+                        shown_second = Some(second);
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+
+            frontend.show_update(&session, update)?;
+            frontend.show_status(&session)?;
+        }
+    }
+
+    let failures = if session.is_completed() {
+        let end = session.metrics().end().unwrap_or_else(|| frontend.now());
+        lesson.pass_criteria().failures(session.metrics(), end)
     } else {
-        style(input_char).black().on_red()
+        vec!["The lesson wasn't finished".to_string()]
     };
 
-    execute!(stdout, PrintStyledContent(content));
+    frontend.show_summary(session.metrics(), &failures)?;
+    let next = frontend.ask_next_lesson(failures.is_empty())?;
+
+    Ok(LessonOutcome {
+        completed: session.is_completed(),
+        passed: failures.is_empty(),
+        events: session.events().to_vec(),
+        metrics: session.into_metrics(),
+        next,
+    })
 }
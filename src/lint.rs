@@ -0,0 +1,125 @@
+use crate::layout::Layout;
+use crate::Lesson;
+use indexmap::map::IndexMap;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Something wrong with a line of a lesson file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub path: PathBuf,
+    /// The line number in the file, counting from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.path.display(), self.line, self.message)
+    }
+}
+
+/// Checks the practice text of every lesson loaded from a file for things that get in the
+/// way of typing it:
+///
+/// - trailing whitespace, which can't be seen
+/// - tabs and other characters that aren't printable ASCII
+/// - lines wider than `width` columns
+/// - keys used before the lesson that introduces them, the first lesson listing them in its
+///   `keys`, going by the key of `layout` that types them
+///
+/// Only keys listed in the `keys` of some lesson are checked for being used too early, keys
+/// no lesson lists can be used anywhere. None of the bundled lessons list their keys.
+///
+/// Built in lessons aren't checked, but they count for when keys are introduced.
+pub fn lint_lessons(
+    lessons: &IndexMap<String, Lesson>,
+    layout: &Layout,
+    width: usize,
+) -> Vec<Problem> {
+    // characters are compared by their key, so `U` and `u` are the same
+    let key = |character: char| {
+        layout
+            .key_for(character)
+            .map_or(character, |key| key.unshifted)
+    };
+
+    // the key, and the position and id of the lesson introducing it
+    let mut introduced: HashMap<char, (usize, &str)> = HashMap::new();
+    for (position, lesson) in lessons.values().enumerate() {
+        for &character in &lesson.meta.keys {
+            introduced
+                .entry(key(character))
+                .or_insert((position, lesson.id()));
+        }
+    }
+
+    let mut problems = Vec::new();
+
+    for (position, lesson) in lessons.values().enumerate() {
+        let file = match lesson.file() {
+            Some(file) => file,
+            None => continue,
+        };
+        let mut problem = |line: usize, message: String| {
+            problems.push(Problem {
+                path: file.path.clone(),
+                line,
+                message,
+            })
+        };
+        // keys used too early are reported once per lesson
+        let mut reported: Vec<char> = Vec::new();
+
+        for (index, text) in lesson.text.split('\n').enumerate() {
+            let line = file.text_line + index;
+
+            if text.ends_with(char::is_whitespace) {
+                problem(line, "trailing whitespace can't be seen".to_string());
+            }
+            if text.contains('\t') {
+                problem(line, "tabs can't be told apart from spaces".to_string());
+            }
+            if let Some(character) = text
+                .chars()
+                .find(|&c| c != '\t' && !(c == ' ' || c.is_ascii_graphic()))
+            {
+                problem(
+                    line,
+                    format!("{:?} isn't a printable ASCII character", character),
+                );
+            }
+
+            let length = text.chars().count();
+            if length > width {
+                problem(
+                    line,
+                    format!(
+                        "the line is {} characters wide, more than {}",
+                        length, width
+                    ),
+                );
+            }
+
+            for character in text.chars() {
+                let early = match introduced.get(&key(character)) {
+                    Some(&(introduced_at, id)) if introduced_at > position => id,
+                    _ => continue,
+                };
+                if !reported.contains(&key(character)) {
+                    reported.push(key(character));
+                    problem(
+                        line,
+                        format!(
+                            "{:?} is used before lesson {} introduces it",
+                            character, early
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    problems
+}
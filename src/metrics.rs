@@ -0,0 +1,306 @@
+use std::time::{Duration, Instant};
+
+/// The number of characters that count as one "word" when computing WPM.
+const CHARS_PER_WORD: f64 = 5.0;
+
+/// Keystroke and timing counters for a single lesson session.
+///
+/// Every method takes the current `Instant` instead of reading the clock itself so the
+/// numbers can be reproduced from recorded input.
+#[derive(Debug, Clone, Default)]
+pub struct SessionMetrics {
+    start: Option<Instant>,
+    end: Option<Instant>,
+    correct_keystrokes: u32,
+    incorrect_keystrokes: u32,
+    corrected_errors: u32,
+    uncorrected_errors: u32,
+}
+
+impl SessionMetrics {
+    pub fn new() -> SessionMetrics {
+        SessionMetrics::default()
+    }
+
+    /// Records a typed character, starting the clock on the first keystroke.
+    pub fn record_keystroke(&mut self, correct: bool, now: Instant) {
+        if self.start.is_none() {
+            self.start = Some(now);
+        }
+
+        if correct {
+            self.correct_keystrokes += 1;
+        } else {
+            self.incorrect_keystrokes += 1;
+            self.uncorrected_errors += 1;
+        }
+    }
+
+    /// Records a backspace over a previously typed character.
+    pub fn record_backspace(&mut self, was_error: bool) {
+        if was_error && self.uncorrected_errors > 0 {
+            self.uncorrected_errors -= 1;
+            self.corrected_errors += 1;
+        }
+    }
+
+    /// Stops the clock. Further calls have no effect.
+    pub fn finish(&mut self, now: Instant) {
+        if self.end.is_none() {
+            self.end = Some(now);
+        }
+    }
+
+    pub fn start(&self) -> Option<Instant> {
+        self.start
+    }
+
+    pub fn end(&self) -> Option<Instant> {
+        self.end
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.end.is_some()
+    }
+
+    pub fn correct_keystrokes(&self) -> u32 {
+        self.correct_keystrokes
+    }
+
+    pub fn incorrect_keystrokes(&self) -> u32 {
+        self.incorrect_keystrokes
+    }
+
+    pub fn total_keystrokes(&self) -> u32 {
+        self.correct_keystrokes + self.incorrect_keystrokes
+    }
+
+    pub fn corrected_errors(&self) -> u32 {
+        self.corrected_errors
+    }
+
+    pub fn uncorrected_errors(&self) -> u32 {
+        self.uncorrected_errors
+    }
+
+    /// Time between the first keystroke and `now`, or the end of the session if it finished.
+    pub fn elapsed(&self, now: Instant) -> Duration {
+        match self.start {
+            Some(start) => self.end.unwrap_or(now).saturating_duration_since(start),
+            None => Duration::from_secs(0),
+        }
+    }
+
+    /// Words per minute counting every keystroke, right or wrong.
+    pub fn gross_wpm(&self, now: Instant) -> f64 {
+        let minutes = self.minutes(now);
+        if minutes <= 0.0 {
+            return 0.0;
+        }
+        f64::from(self.total_keystrokes()) / CHARS_PER_WORD / minutes
+    }
+
+    /// Gross WPM minus one word per minute for every error left uncorrected.
+    pub fn net_wpm(&self, now: Instant) -> f64 {
+        let minutes = self.minutes(now);
+        if minutes <= 0.0 {
+            return 0.0;
+        }
+        let net = self.gross_wpm(now) - f64::from(self.uncorrected_errors) / minutes;
+        net.max(0.0)
+    }
+
+    /// Percentage of keystrokes that matched the expected character.
+    pub fn accuracy(&self) -> f64 {
+        let total = self.total_keystrokes();
+        if total == 0 {
+            return 100.0;
+        }
+        f64::from(self.correct_keystrokes) / f64::from(total) * 100.0
+    }
+
+    /// One line summary shown below the practice text while typing.
+    pub fn status_line(&self, now: Instant) -> String {
+        format!(
+            "WPM: {:.0}  Net: {:.0}  Accuracy: {:.1}%  Errors: {} ({} corrected)  Time: {}",
+            self.gross_wpm(now),
+            self.net_wpm(now),
+            self.accuracy(),
+            self.incorrect_keystrokes,
+            self.corrected_errors,
+            format_duration(self.elapsed(now))
+        )
+    }
+
+    /// The lines shown on the summary screen once the lesson is finished.
+    pub fn summary(&self, now: Instant) -> Vec<String> {
+        vec![
+            format!("Time:               {}", format_duration(self.elapsed(now))),
+            format!("Gross WPM:          {:.1}", self.gross_wpm(now)),
+            format!("Net WPM:            {:.1}", self.net_wpm(now)),
+            format!("Accuracy:           {:.1}%", self.accuracy()),
+            format!("Correct keystrokes: {}", self.correct_keystrokes),
+            format!("Wrong keystrokes:   {}", self.incorrect_keystrokes),
+            format!("Corrected errors:   {}", self.corrected_errors),
+            format!("Uncorrected errors: {}", self.uncorrected_errors),
+        ]
+    }
+
+    fn minutes(&self, now: Instant) -> f64 {
+        self.elapsed(now).as_secs_f64() / 60.0
+    }
+}
+
+/// What a finished lesson needs to count as passed. Criteria that aren't set always pass.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PassCriteria {
+    /// Minimum accuracy in percent.
+    pub min_accuracy: Option<f64>,
+    pub min_net_wpm: Option<f64>,
+    pub max_uncorrected_errors: Option<u32>,
+}
+
+impl PassCriteria {
+    /// These criteria, with the ones that aren't set taken from `defaults`.
+    pub fn or(self, defaults: PassCriteria) -> PassCriteria {
+        PassCriteria {
+            min_accuracy: self.min_accuracy.or(defaults.min_accuracy),
+            min_net_wpm: self.min_net_wpm.or(defaults.min_net_wpm),
+            max_uncorrected_errors: self
+                .max_uncorrected_errors
+                .or(defaults.max_uncorrected_errors),
+        }
+    }
+
+    /// Why a session with `metrics` doesn't pass, nothing if it does.
+    pub fn failures(&self, metrics: &SessionMetrics, now: Instant) -> Vec<String> {
+        let mut failures = Vec::new();
+
+        if let Some(min_accuracy) = self.min_accuracy {
+            if metrics.accuracy() < min_accuracy {
+                failures.push(format!(
+                    "Accuracy {:.1}% is below {:.1}%",
+                    metrics.accuracy(),
+                    min_accuracy
+                ));
+            }
+        }
+        if let Some(min_net_wpm) = self.min_net_wpm {
+            if metrics.net_wpm(now) < min_net_wpm {
+                failures.push(format!(
+                    "Net WPM {:.1} is below {:.1}",
+                    metrics.net_wpm(now),
+                    min_net_wpm
+                ));
+            }
+        }
+        if let Some(max_errors) = self.max_uncorrected_errors {
+            if metrics.uncorrected_errors() > max_errors {
+                failures.push(format!(
+                    "{} uncorrected errors, at most {} allowed",
+                    metrics.uncorrected_errors(),
+                    max_errors
+                ));
+            }
+        }
+
+        failures
+    }
+}
+
+/// Formats a duration as minutes and seconds, like 1:05.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 60 keystrokes over a minute, 6 of them wrong and 2 of those corrected.
+    fn minute_of_typing(start: Instant) -> SessionMetrics {
+        let mut metrics = SessionMetrics::new();
+        for index in 0..60 {
+            let now = start + Duration::from_secs(index);
+            metrics.record_keystroke(index % 10 != 0, now);
+        }
+        metrics.record_backspace(true);
+        metrics.record_backspace(true);
+        metrics.record_backspace(false);
+        metrics.finish(start + Duration::from_secs(60));
+        metrics
+    }
+
+    #[test]
+    fn computes_wpm_and_accuracy() {
+        let start = Instant::now();
+        let metrics = minute_of_typing(start);
+        let later = start + Duration::from_secs(600);
+
+        assert_eq!(metrics.elapsed(later), Duration::from_secs(60));
+        assert_eq!(metrics.gross_wpm(later), 12.0);
+        assert_eq!(metrics.net_wpm(later), 8.0);
+        assert_eq!(metrics.accuracy(), 90.0);
+        assert_eq!(metrics.corrected_errors(), 2);
+        assert_eq!(metrics.uncorrected_errors(), 4);
+    }
+
+    #[test]
+    fn sessions_without_time_or_keystrokes_have_no_speed() {
+        let start = Instant::now();
+
+        let metrics = SessionMetrics::new();
+        assert_eq!(metrics.elapsed(start), Duration::from_secs(0));
+        assert_eq!(metrics.gross_wpm(start), 0.0);
+        assert_eq!(metrics.net_wpm(start), 0.0);
+        assert_eq!(metrics.accuracy(), 100.0);
+
+        let mut metrics = SessionMetrics::new();
+        metrics.record_keystroke(false, start);
+        assert_eq!(metrics.gross_wpm(start), 0.0);
+        assert_eq!(metrics.net_wpm(start), 0.0);
+        assert_eq!(metrics.accuracy(), 0.0);
+
+        // net WPM doesn't go below zero
+        assert_eq!(metrics.net_wpm(start + Duration::from_secs(1)), 0.0);
+    }
+
+    #[test]
+    fn fails_sessions_below_the_pass_criteria() {
+        let start = Instant::now();
+        let metrics = minute_of_typing(start);
+
+        assert!(PassCriteria::default().failures(&metrics, start).is_empty());
+
+        let at_the_limits = PassCriteria {
+            min_accuracy: Some(90.0),
+            min_net_wpm: Some(8.0),
+            max_uncorrected_errors: Some(4),
+        };
+        assert!(at_the_limits.failures(&metrics, start).is_empty());
+
+        let stricter = PassCriteria {
+            min_accuracy: Some(95.0),
+            min_net_wpm: Some(10.0),
+            max_uncorrected_errors: Some(3),
+        };
+        assert_eq!(
+            stricter.failures(&metrics, start),
+            vec![
+                "Accuracy 90.0% is below 95.0%",
+                "Net WPM 8.0 is below 10.0",
+                "4 uncorrected errors, at most 3 allowed"
+            ]
+        );
+
+        // criteria that aren't set come from the defaults
+        let criteria = PassCriteria {
+            min_accuracy: Some(95.0),
+            ..PassCriteria::default()
+        }
+        .or(at_the_limits);
+        assert_eq!(criteria.min_accuracy, Some(95.0));
+        assert_eq!(criteria.max_uncorrected_errors, Some(4));
+    }
+}
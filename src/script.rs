@@ -0,0 +1,232 @@
+use crate::frontend::{next_lesson_question, Frontend};
+use crate::metrics::SessionMetrics;
+use crate::session::{TypingSession, Update};
+use crate::{Event, Lesson};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Time between events of a script that don't say when they happen, 48 WPM when typing.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// An event of a script and when it happens, relative to the start of the script.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScriptedEvent {
+    pub at: Duration,
+    pub event: Event,
+}
+
+/// A frontend that plays back a script of events instead of reading the keyboard, for
+/// running lessons in tests.
+///
+/// Nothing is drawn, but the text that would be shown is collected in `transcript`. Once
+/// the script runs out a running lesson is quit and further questions are answered with no.
+pub struct ScriptedFrontend {
+    events: VecDeque<ScriptedEvent>,
+    start: Instant,
+    /// When the last event returned by `next_event` happened.
+    now: Instant,
+    transcript: Vec<String>,
+}
+
+impl ScriptedFrontend {
+    pub fn new(events: impl IntoIterator<Item = ScriptedEvent>) -> ScriptedFrontend {
+        let start = Instant::now();
+
+        ScriptedFrontend {
+            events: events.into_iter().collect(),
+            start,
+            now: start,
+            transcript: Vec::new(),
+        }
+    }
+
+    /// Plays back `events` one `DEFAULT_INTERVAL` apart.
+    pub fn from_events(events: impl IntoIterator<Item = Event>) -> ScriptedFrontend {
+        ScriptedFrontend::new(
+            events
+                .into_iter()
+                .enumerate()
+                .map(|(index, event)| ScriptedEvent {
+                    at: DEFAULT_INTERVAL * (index as u32 + 1),
+                    event,
+                }),
+        )
+    }
+
+    /// Titles, lines, summaries and questions in the order they would have been shown.
+    pub fn transcript(&self) -> &[String] {
+        &self.transcript
+    }
+
+    /// Whether every event of the script was used.
+    pub fn is_finished(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    fn pop_event(&mut self) -> Option<Event> {
+        let scripted = self.events.pop_front()?;
+        self.now = self.start + scripted.at;
+        Some(scripted.event)
+    }
+}
+
+impl Frontend for ScriptedFrontend {
+    type Error = crossterm::ErrorKind;
+
+    fn start_lesson(&mut self, lesson: &Lesson) -> crossterm::Result<()> {
+        self.transcript.push(lesson.title().to_string());
+        if let Some(description) = &lesson.meta().description {
+            self.transcript.push(description.clone());
+        }
+        Ok(())
+    }
+
+    fn show_line(&mut self, session: &TypingSession) -> crossterm::Result<()> {
+        let line = session.line().unwrap_or_default().iter().collect();
+        self.transcript.push(line);
+        Ok(())
+    }
+
+    fn show_update(&mut self, _session: &TypingSession, _update: Update) -> crossterm::Result<()> {
+        Ok(())
+    }
+
+    fn show_status(&mut self, _session: &TypingSession) -> crossterm::Result<()> {
+        Ok(())
+    }
+
+    fn next_event(&mut self) -> crossterm::Result<Option<Event>> {
+        Ok(Some(self.pop_event().unwrap_or(Event::Quit)))
+    }
+
+    fn now(&self) -> Instant {
+        self.now
+    }
+
+    fn show_summary(
+        &mut self,
+        metrics: &SessionMetrics,
+        failures: &[String],
+    ) -> crossterm::Result<()> {
+        self.transcript.extend(metrics.summary(self.now));
+        self.transcript.extend(failures.iter().cloned());
+        Ok(())
+    }
+
+    fn ask_next_lesson(&mut self, passed: bool) -> crossterm::Result<bool> {
+        self.transcript
+            .push(next_lesson_question(passed).to_string());
+
+        let mut answer = false;
+
+        loop {
+            match self.pop_event() {
+                Some(Event::InputCharacter(character)) => match character.to_ascii_lowercase() {
+                    'y' => answer = true,
+                    'n' => answer = false,
+                    _ => {}
+                },
+                Some(Event::NewLine) => return Ok(answer),
+                Some(Event::Quit) | None => return Ok(false),
+                Some(Event::BackSpace) => {}
+            }
+        }
+    }
+}
+
+/// Parses a script, one event per line:
+///
+/// ```text
+/// # comments start with a hash
+/// type uuuu hhhh
+/// enter
+/// @5000 backspace
+/// quit
+/// ```
+///
+/// `type TEXT` types each character of the rest of the line, spaces included, `enter`,
+/// `backspace` and `quit` press those keys. A line can start with `@MS`, the milliseconds
+/// since the start of the script its first event happens at, otherwise events are
+/// `DEFAULT_INTERVAL` apart.
+pub fn parse_script(source: &str) -> Result<Vec<ScriptedEvent>, String> {
+    let mut events = Vec::new();
+    let mut at = Duration::from_secs(0);
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let mut line = line.trim_start();
+
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut timestamp = None;
+        if let Some(rest) = line.strip_prefix('@') {
+            let end = rest.find(' ').unwrap_or(rest.len());
+            let milliseconds = rest[..end]
+                .parse()
+                .map_err(|_| format!("line {}: invalid time \"{}\"", line_number, &rest[..end]))?;
+            timestamp = Some(Duration::from_millis(milliseconds));
+            line = rest[end..].trim_start();
+        }
+
+        let (command, argument) = match line.find(' ') {
+            Some(space) => (&line[..space], &line[space + 1..]),
+            None => (line.trim_end(), ""),
+        };
+
+        let line_events = match command {
+            "type" if !argument.is_empty() => argument.chars().map(Event::InputCharacter).collect(),
+            "enter" => vec![Event::NewLine],
+            "backspace" => vec![Event::BackSpace],
+            "quit" => vec![Event::Quit],
+            _ => return Err(format!("line {}: unknown event \"{}\"", line_number, line)),
+        };
+
+        for (event_index, event) in line_events.into_iter().enumerate() {
+            at = match timestamp {
+                Some(timestamp) if event_index == 0 => timestamp,
+                _ => at + DEFAULT_INTERVAL,
+            };
+            events.push(ScriptedEvent { at, event });
+        }
+    }
+
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_scripts_with_timestamps() {
+        let events =
+            parse_script("# a comment\n@1000 type ab\nenter\n\n@5000 backspace\nquit").unwrap();
+        let times: Vec<Duration> = events.iter().map(|event| event.at).collect();
+
+        assert_eq!(
+            events.iter().map(|event| event.event).collect::<Vec<_>>(),
+            vec![
+                Event::InputCharacter('a'),
+                Event::InputCharacter('b'),
+                Event::NewLine,
+                Event::BackSpace,
+                Event::Quit
+            ]
+        );
+        assert_eq!(
+            times,
+            vec![
+                Duration::from_millis(1000),
+                Duration::from_millis(1250),
+                Duration::from_millis(1500),
+                Duration::from_millis(5000),
+                Duration::from_millis(5250)
+            ]
+        );
+
+        assert!(parse_script("jump").is_err());
+        assert!(parse_script("@soon enter").is_err());
+    }
+}
@@ -0,0 +1,426 @@
+use crate::metrics::SessionMetrics;
+use crate::{Event, Lesson};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// The state of typing through a lesson, without any terminal or other user interface.
+///
+/// Events go in through `handle` together with the time they happened, the returned `Update`
+/// says what changed so a frontend can draw it and statistics can be recorded.
+#[derive(Debug, Clone)]
+pub struct TypingSession {
+    lines: Vec<Vec<char>>,
+    line_index: usize,
+    /// What was typed on the current line so far.
+    typed: Vec<char>,
+    /// Type the indentation of each line automatically, see `LessonMeta::auto_indent`.
+    auto_indent: bool,
+    /// How much of `typed` is indentation that was typed automatically and can't be erased.
+    indent: usize,
+    metrics: SessionMetrics,
+    last_keystroke: Option<Instant>,
+    quit: bool,
+    /// How long the session may take from the first keystroke, for timed lessons.
+    time_limit: Option<Duration>,
+    timed_out: bool,
+    /// When the first event happened, times in `events` are relative to it.
+    first_event: Option<Instant>,
+    events: Vec<LoggedEvent>,
+}
+
+/// An event handled by a session, kept for the keystroke log.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoggedEvent {
+    /// Time since the first event of the session.
+    pub time: Duration,
+    pub event: Event,
+    /// The character that was expected when the event happened, `'\n'` at the end of a line.
+    pub expected: char,
+}
+
+/// A typed character, compared to the one the lesson expected.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keystroke {
+    /// The expected character before this one on the same line.
+    pub previous: Option<char>,
+    pub expected: char,
+    pub typed: char,
+    /// Time since the previous keystroke of the session, if there was one.
+    pub latency: Option<Duration>,
+}
+
+impl Keystroke {
+    pub fn is_correct(&self) -> bool {
+        self.expected == self.typed
+    }
+}
+
+/// What an event changed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Update {
+    /// The event doesn't apply right now, like a new line before the line is typed.
+    Ignored,
+    /// A character was typed at the cursor.
+    Typed(Keystroke),
+    /// The character before the cursor was erased.
+    Erased,
+    /// The line was finished and the cursor moved to the start of the next one.
+    NextLine,
+    /// The last line was finished.
+    Completed,
+    /// The time limit of a timed lesson ran out.
+    TimeUp,
+    /// The user gave up on the lesson.
+    Quit,
+}
+
+impl TypingSession {
+    pub fn new(lesson: &Lesson) -> TypingSession {
+        let mut session = TypingSession {
+            lines: lesson
+                .text()
+                .lines()
+                .map(|line| line.chars().collect())
+                .collect(),
+            line_index: 0,
+            typed: Vec::new(),
+            auto_indent: lesson.meta().auto_indent,
+            indent: 0,
+            metrics: SessionMetrics::new(),
+            last_keystroke: None,
+            quit: false,
+            time_limit: lesson
+                .meta()
+                .time_limit
+                // limits too big for a `Duration`, like `inf`, are no limit
+                .and_then(|seconds| Duration::try_from_secs_f64(seconds.max(0.0)).ok()),
+            timed_out: false,
+            first_event: None,
+            events: Vec::new(),
+        };
+        session.type_indentation();
+        session
+    }
+
+    /// Applies an event that happened at `now`. Events after the time limit aren't applied,
+    /// the session ends instead.
+    pub fn handle(&mut self, event: Event, now: Instant) -> Update {
+        if self.is_finished() {
+            return Update::Ignored;
+        }
+        if self.check_time(now) == Update::TimeUp {
+            return Update::TimeUp;
+        }
+
+        let first_event = *self.first_event.get_or_insert(now);
+        self.events.push(LoggedEvent {
+            time: now.saturating_duration_since(first_event),
+            event,
+            expected: self.next_char().unwrap_or('\n'),
+        });
+
+        let line = &self.lines[self.line_index];
+
+        match event {
+            // at the end of the line only a new line is accepted
+            Event::InputCharacter(character) if self.typed.len() < line.len() => {
+                let keystroke = Keystroke {
+                    // automatically typed indentation doesn't count as a previous keystroke
+                    previous: self
+                        .typed
+                        .len()
+                        .checked_sub(1)
+                        .filter(|&index| index >= self.indent)
+                        .map(|index| line[index]),
+                    expected: line[self.typed.len()],
+                    typed: character,
+                    latency: self.last_keystroke.map(|last| now - last),
+                };
+                self.metrics.record_keystroke(keystroke.is_correct(), now);
+                self.last_keystroke = Some(now);
+                self.typed.push(character);
+                Update::Typed(keystroke)
+            }
+            Event::NewLine if self.typed.len() == line.len() => {
+                self.last_keystroke = Some(now);
+                self.typed.clear();
+                self.line_index += 1;
+                self.type_indentation();
+
+                if self.line_index < self.lines.len() {
+                    Update::NextLine
+                } else {
+                    self.metrics.finish(now);
+                    Update::Completed
+                }
+            }
+            Event::BackSpace if self.typed.len() > self.indent => {
+                let index = self.typed.len() - 1;
+                let was_error = self.typed[index] != line[index];
+                self.typed.pop();
+                self.metrics.record_backspace(was_error);
+                self.last_keystroke = Some(now);
+                Update::Erased
+            }
+            Event::Quit => {
+                self.quit = true;
+                self.metrics.finish(now);
+                Update::Quit
+            }
+            _ => Update::Ignored,
+        }
+    }
+
+    /// Starts the current line with its indentation typed, if that's done automatically.
+    fn type_indentation(&mut self) {
+        self.indent = 0;
+        if !self.auto_indent {
+            return;
+        }
+
+        if let Some(line) = self.lines.get(self.line_index) {
+            self.indent = line.iter().take_while(|&&c| c == ' ').count();
+            self.typed.extend_from_slice(&line[..self.indent]);
+        }
+    }
+
+    /// Ends a timed session once its time limit ran out, returning `Update::TimeUp` if it
+    /// did. The clock starts with the first keystroke.
+    pub fn check_time(&mut self, now: Instant) -> Update {
+        let (limit, start) = match (self.time_limit, self.metrics.start()) {
+            (Some(limit), Some(start)) if !self.is_finished() => (limit, start),
+            _ => return Update::Ignored,
+        };
+
+        if now.saturating_duration_since(start) < limit {
+            return Update::Ignored;
+        }
+
+        self.timed_out = true;
+        self.metrics.finish(start + limit);
+        Update::TimeUp
+    }
+
+    /// The time limit of a timed lesson.
+    pub fn time_limit(&self) -> Option<Duration> {
+        self.time_limit
+    }
+
+    /// All lines of the lesson.
+    pub fn lines(&self) -> &[Vec<char>] {
+        &self.lines
+    }
+
+    /// Index of the line being typed, the number of lines once the lesson is completed.
+    pub fn line_index(&self) -> usize {
+        self.line_index
+    }
+
+    /// The line being typed, `None` once the session is finished.
+    pub fn line(&self) -> Option<&[char]> {
+        if self.is_finished() {
+            return None;
+        }
+        self.lines.get(self.line_index).map(Vec::as_slice)
+    }
+
+    /// Column of the cursor on the current line, the number of characters typed on it.
+    pub fn cursor(&self) -> usize {
+        self.typed.len()
+    }
+
+    /// The characters typed on the current line, including indentation typed automatically.
+    pub fn typed(&self) -> &[char] {
+        &self.typed
+    }
+
+    /// Whether each character typed on the current line matches the lesson.
+    pub fn correctness(&self) -> Vec<bool> {
+        self.typed
+            .iter()
+            .zip(self.line().unwrap_or_default())
+            .map(|(typed, expected)| typed == expected)
+            .collect()
+    }
+
+    /// The character to type next, `None` at the end of a line, where a new line is next.
+    pub fn next_char(&self) -> Option<char> {
+        self.line()?.get(self.typed.len()).cloned()
+    }
+
+    /// Whether every line was typed, or typing went on until the time limit.
+    pub fn is_completed(&self) -> bool {
+        !self.quit && (self.timed_out || self.line_index >= self.lines.len())
+    }
+
+    /// Whether the session is over, because it was completed or the user quit.
+    pub fn is_finished(&self) -> bool {
+        self.quit || self.timed_out || self.line_index >= self.lines.len()
+    }
+
+    /// Every event handled so far, including ones that were ignored.
+    pub fn events(&self) -> &[LoggedEvent] {
+        &self.events
+    }
+
+    pub fn metrics(&self) -> &SessionMetrics {
+        &self.metrics
+    }
+
+    pub fn into_metrics(self) -> SessionMetrics {
+        self.metrics
+    }
+}
+
+/// How often each expected character was mistyped in `events`, most missed first.
+pub fn missed_characters(events: &[LoggedEvent]) -> Vec<(char, u32)> {
+    let mut missed: BTreeMap<char, u32> = BTreeMap::new();
+
+    for logged in events {
+        if let Event::InputCharacter(typed) = logged.event {
+            if typed != logged.expected && logged.expected != '\n' {
+                *missed.entry(logged.expected).or_insert(0) += 1;
+            }
+        }
+    }
+
+    let mut missed: Vec<(char, u32)> = missed.into_iter().collect();
+    missed.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+    missed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LessonMeta;
+
+    fn session(text: &str) -> TypingSession {
+        let lesson = Lesson::new(
+            "01".to_string(),
+            "Test".to_string(),
+            text.to_string(),
+            LessonMeta::default(),
+        );
+        TypingSession::new(&lesson)
+    }
+
+    fn type_text(session: &mut TypingSession, text: &str, now: Instant) -> Vec<Update> {
+        text.chars()
+            .map(|character| {
+                let event = match character {
+                    '\n' => Event::NewLine,
+                    '\x08' => Event::BackSpace,
+                    character => Event::InputCharacter(character),
+                };
+                session.handle(event, now)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn compares_keystrokes_to_the_lesson() {
+        let start = Instant::now();
+        let mut session = session("ab\ncd");
+
+        assert_eq!(
+            session.handle(Event::InputCharacter('a'), start),
+            Update::Typed(Keystroke {
+                previous: None,
+                expected: 'a',
+                typed: 'a',
+                latency: None,
+            })
+        );
+        // a new line is only accepted at the end of the line
+        assert_eq!(session.handle(Event::NewLine, start), Update::Ignored);
+        assert_eq!(
+            session.handle(
+                Event::InputCharacter('x'),
+                start + Duration::from_millis(300)
+            ),
+            Update::Typed(Keystroke {
+                previous: Some('a'),
+                expected: 'b',
+                typed: 'x',
+                latency: Some(Duration::from_millis(300)),
+            })
+        );
+        // and at the end of the line nothing else
+        assert_eq!(
+            session.handle(Event::InputCharacter('c'), start),
+            Update::Ignored
+        );
+
+        assert_eq!(session.typed(), &['a', 'x']);
+        assert_eq!(session.correctness(), vec![true, false]);
+        assert_eq!(session.next_char(), None);
+        assert_eq!(session.metrics().correct_keystrokes(), 1);
+        assert_eq!(session.metrics().incorrect_keystrokes(), 1);
+        assert_eq!(session.metrics().uncorrected_errors(), 1);
+        assert_eq!(session.events().len(), 4);
+    }
+
+    #[test]
+    fn backspace_erases_within_the_line() {
+        let start = Instant::now();
+        let mut session = session("ab\ncd");
+
+        assert_eq!(session.handle(Event::BackSpace, start), Update::Ignored);
+        type_text(&mut session, "ax", start);
+        assert_eq!(session.handle(Event::BackSpace, start), Update::Erased);
+        assert_eq!(session.cursor(), 1);
+        type_text(&mut session, "b\n", start);
+
+        // the previous line can't be erased
+        assert_eq!(session.handle(Event::BackSpace, start), Update::Ignored);
+        assert_eq!(session.line_index(), 1);
+        assert_eq!(session.metrics().incorrect_keystrokes(), 1);
+        assert_eq!(session.metrics().corrected_errors(), 1);
+        assert_eq!(session.metrics().uncorrected_errors(), 0);
+    }
+
+    #[test]
+    fn finishes_at_the_end_of_the_lesson() {
+        let start = Instant::now();
+        let end = start + Duration::from_secs(2);
+        let mut session = session("ab\nc");
+
+        assert_eq!(type_text(&mut session, "ab\n", start)[2], Update::NextLine);
+        assert!(!session.is_finished());
+
+        assert_eq!(type_text(&mut session, "c\n", end)[1], Update::Completed);
+        assert!(session.is_completed());
+        assert!(session.is_finished());
+        assert_eq!(session.line(), None);
+        assert_eq!(session.metrics().end(), Some(end));
+        assert_eq!(
+            session.handle(Event::InputCharacter('d'), end),
+            Update::Ignored
+        );
+    }
+
+    #[test]
+    fn quitting_does_not_complete_the_lesson() {
+        let start = Instant::now();
+        let mut session = session("ab");
+
+        type_text(&mut session, "a", start);
+        assert_eq!(session.handle(Event::Quit, start), Update::Quit);
+        assert!(session.is_finished());
+        assert!(!session.is_completed());
+    }
+
+    #[test]
+    fn counts_missed_characters() {
+        let start = Instant::now();
+        let mut session = session("aab\nb");
+
+        // errors count as missed even once they're corrected
+        type_text(&mut session, "xa\x08ab\nz", start);
+
+        assert_eq!(
+            missed_characters(session.events()),
+            vec![('a', 1), ('b', 1)]
+        );
+    }
+}
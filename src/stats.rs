@@ -0,0 +1,248 @@
+use crate::layout::{Finger, Layout, Row};
+use crate::metrics::SessionMetrics;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// How many recent samples the rolling latency average roughly covers.
+const LATENCY_WINDOW: u32 = 50;
+
+/// Everything remembered between runs, persisted to stats.json.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Stats {
+    /// The most recently completed lesson, `--continue` starts at the one after it.
+    pub last_lesson_id: String,
+    /// Progress per lesson id.
+    pub lessons: BTreeMap<String, LessonProgress>,
+    /// Per target character statistics.
+    pub keys: BTreeMap<char, KeyStats>,
+    /// Statistics for typing the second character of each two character sequence.
+    pub bigrams: BTreeMap<String, KeyStats>,
+}
+
+/// How far the user got with a single lesson.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct LessonProgress {
+    /// Whether the lesson was passed, typed to the end while meeting its pass criteria.
+    pub completed: bool,
+    pub attempts: u32,
+    pub best_net_wpm: f64,
+    pub best_accuracy: f64,
+}
+
+/// Hit, miss and timing counters for a key, or a group of keys.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct KeyStats {
+    pub hits: u32,
+    pub misses: u32,
+    /// What was typed instead of this key, and how often.
+    pub substitutions: BTreeMap<char, u32>,
+    /// Rolling average of the time between the previous keystroke and this one.
+    pub average_latency_ms: f64,
+    pub latency_samples: u32,
+}
+
+impl KeyStats {
+    pub fn attempts(&self) -> u32 {
+        self.hits + self.misses
+    }
+
+    /// Percentage of attempts that were misses.
+    pub fn error_rate(&self) -> f64 {
+        if self.attempts() == 0 {
+            return 0.0;
+        }
+        f64::from(self.misses) / f64::from(self.attempts()) * 100.0
+    }
+
+    fn record(&mut self, typed: Option<char>, latency: Option<Duration>) {
+        match typed {
+            None => self.hits += 1,
+            Some(typed) => {
+                self.misses += 1;
+                *self.substitutions.entry(typed).or_insert(0) += 1;
+            }
+        }
+
+        if let Some(latency) = latency {
+            let sample = latency.as_secs_f64() * 1000.0;
+            self.latency_samples += 1;
+            let weight = f64::from(self.latency_samples.min(LATENCY_WINDOW));
+            self.average_latency_ms += (sample - self.average_latency_ms) / weight;
+        }
+    }
+
+    /// Adds the counters of `other` into these, weighting the latencies by sample count.
+    fn merge(&mut self, other: &KeyStats) {
+        let samples = self.latency_samples + other.latency_samples;
+        if samples > 0 {
+            self.average_latency_ms = (self.average_latency_ms * f64::from(self.latency_samples)
+                + other.average_latency_ms * f64::from(other.latency_samples))
+                / f64::from(samples);
+        }
+        self.latency_samples = samples;
+
+        self.hits += other.hits;
+        self.misses += other.misses;
+        for (typed, count) in &other.substitutions {
+            *self.substitutions.entry(*typed).or_insert(0) += count;
+        }
+    }
+}
+
+impl Stats {
+    /// Records one keystroke against the character the lesson expected.
+    ///
+    /// `previous` is the expected character before this one on the same line and `latency`
+    /// the time since the previous keystroke, if there was one.
+    pub fn record_keystroke(
+        &mut self,
+        previous: Option<char>,
+        expected: char,
+        typed: char,
+        latency: Option<Duration>,
+    ) {
+        let substitution = if typed == expected { None } else { Some(typed) };
+        self.keys
+            .entry(expected)
+            .or_default()
+            .record(substitution, latency);
+
+        if let Some(previous) = previous {
+            self.bigrams
+                .entry(format!("{}{}", previous, expected))
+                .or_default()
+                .record(substitution, latency);
+        }
+    }
+
+    /// Records the outcome of a lesson run. Only completed runs count towards the best
+    /// results, and only passed ones mark the lesson completed and move `last_lesson_id`
+    /// forward.
+    pub fn record_lesson(
+        &mut self,
+        lesson_id: &str,
+        metrics: &SessionMetrics,
+        completed: bool,
+        passed: bool,
+    ) {
+        let progress = self.lessons.entry(lesson_id.to_string()).or_default();
+        progress.attempts += 1;
+
+        if !completed {
+            return;
+        }
+
+        let now = metrics.end().unwrap_or_else(Instant::now);
+        progress.best_net_wpm = progress.best_net_wpm.max(metrics.net_wpm(now));
+        progress.best_accuracy = progress.best_accuracy.max(metrics.accuracy());
+
+        if passed {
+            progress.completed = true;
+            self.last_lesson_id = lesson_id.to_string();
+        }
+    }
+
+    /// Key statistics rolled up to the finger that types each key on `layout`.
+    pub fn finger_stats(&self, layout: &Layout) -> BTreeMap<Finger, KeyStats> {
+        self.roll_up(|character| layout.finger(character))
+    }
+
+    /// Key statistics rolled up to the keyboard row each key sits on in `layout`.
+    pub fn row_stats(&self, layout: &Layout) -> BTreeMap<Row, KeyStats> {
+        self.roll_up(|character| layout.row(character))
+    }
+
+    fn roll_up<K: Ord>(&self, group: impl Fn(char) -> Option<K>) -> BTreeMap<K, KeyStats> {
+        let mut groups: BTreeMap<K, KeyStats> = BTreeMap::new();
+        for (character, stats) in &self.keys {
+            if let Some(key) = group(*character) {
+                groups.entry(key).or_default().merge(stats);
+            }
+        }
+        groups
+    }
+
+    /// A human readable report of the weakest fingers, rows and keys.
+    pub fn report(&self, layout: &Layout) -> Vec<String> {
+        fn line(name: String, stats: &KeyStats) -> String {
+            format!(
+                "  {:<14} {:>6} hits {:>5} misses {:>6.1}% errors {:>6.0} ms",
+                name,
+                stats.hits,
+                stats.misses,
+                stats.error_rate(),
+                stats.average_latency_ms
+            )
+        }
+
+        let mut report = vec!["Fingers:".to_string()];
+        for (finger, stats) in self.finger_stats(layout) {
+            report.push(line(finger.to_string(), &stats));
+        }
+
+        report.push("Rows:".to_string());
+        for (row, stats) in self.row_stats(layout) {
+            report.push(line(row.to_string(), &stats));
+        }
+
+        let mut keys: Vec<(&char, &KeyStats)> = self.keys.iter().collect();
+        keys.sort_by(|a, b| b.1.error_rate().partial_cmp(&a.1.error_rate()).unwrap());
+
+        report.push("Keys, most missed first:".to_string());
+        for (character, stats) in keys {
+            let mut name = format!("{:?}", character);
+            if let Some((typed, _)) = stats.substitutions.iter().max_by_key(|(_, count)| **count) {
+                name = format!("{} ({:?})", name, typed);
+            }
+            report.push(line(name, stats));
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::Finger::LeftPinky;
+    use crate::layout::PROGRAMMER_DVORAK;
+
+    #[test]
+    fn records_keystrokes_and_rolls_them_up_by_finger_and_row() {
+        let ms = Duration::from_millis;
+        let mut stats = Stats::default();
+
+        stats.record_keystroke(None, 'a', 'a', None);
+        stats.record_keystroke(Some('a'), 'a', 'a', Some(ms(100)));
+        stats.record_keystroke(Some('a'), 'a', 'x', Some(ms(300)));
+        // typed with the same finger on the row above
+        stats.record_keystroke(Some('a'), ';', ';', Some(ms(500)));
+
+        let a = &stats.keys[&'a'];
+        assert_eq!((a.hits, a.misses), (2, 1));
+        assert_eq!(a.substitutions[&'x'], 1);
+        // the first keystroke has no latency
+        assert_eq!(a.latency_samples, 2);
+        assert_eq!(a.average_latency_ms, 200.0);
+
+        let aa = &stats.bigrams["aa"];
+        assert_eq!((aa.hits, aa.misses, aa.latency_samples), (1, 1, 2));
+        assert_eq!(stats.bigrams["a;"].hits, 1);
+
+        let fingers = stats.finger_stats(&PROGRAMMER_DVORAK);
+        assert_eq!(fingers.len(), 1);
+        let pinky = &fingers[&LeftPinky];
+        assert_eq!((pinky.hits, pinky.misses), (3, 1));
+        assert_eq!(pinky.latency_samples, 3);
+        assert_eq!(pinky.average_latency_ms, 300.0);
+
+        let rows = stats.row_stats(&PROGRAMMER_DVORAK);
+        assert_eq!(rows[&Row::Home].attempts(), 3);
+        assert_eq!(rows[&Row::Top].attempts(), 1);
+        assert_eq!(rows[&Row::Top].average_latency_ms, 500.0);
+    }
+}
@@ -0,0 +1,588 @@
+use crate::browser::{BrowserAction, BrowserKey, LessonBrowser};
+use crate::code::{self, Language, TokenKind};
+use crate::frontend::{next_lesson_question, Frontend};
+use crate::ghost::GhostPosition;
+use crate::keyboard::{self, Highlight};
+use crate::layout::{self, Layout};
+use crate::metrics::{format_duration, SessionMetrics};
+use crate::session::{TypingSession, Update};
+use crate::text;
+use crate::{Event, Lesson, LessonOptions};
+use crossterm::style::Colorize;
+use crossterm::terminal::size;
+use crossterm::{
+    cursor::{position, Hide, MoveLeft, MoveTo, RestorePosition, SavePosition},
+    execute,
+    input::{input, AsyncReader, InputEvent, KeyEvent},
+    screen::{AlternateScreen, RawScreen},
+    style::{style, PrintStyledContent, StyledContent},
+    terminal::{Clear, ClearType, ScrollUp},
+    utils::Output,
+};
+use indexmap::map::IndexMap;
+use std::io::{stdout, Stdout, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Runs lessons in the terminal: practice text scrolling at the top and a footer with the
+/// optional keyboard diagram and the status line fixed at the bottom.
+///
+/// The terminal is switched to the alternate screen and raw mode for as long as the
+/// frontend lives.
+pub struct TerminalFrontend<'a> {
+    // dropped in order, so raw mode ends before the alternate screen is left
+    _raw: crossterm::Result<RawScreen>,
+    _alternate: AlternateScreen,
+    stdout: Stdout,
+    stdin: AsyncReader,
+    layout: &'a Layout,
+    show_keyboard: bool,
+    /// The layout the keyboard is set to, when emulating `layout` on it.
+    physical_layout: Option<&'a Layout>,
+    /// The character the user should type next, highlighted on the keyboard.
+    next_char: Option<char>,
+    /// The row the line being typed is shown on, above the typed characters.
+    target_row: u16,
+    /// The time limit of the running lesson, to show the time left.
+    time_limit: Option<Duration>,
+    highlight_syntax: bool,
+    /// The kinds of the characters of the running lesson, when its code is highlighted.
+    highlighted: Option<Vec<Vec<TokenKind>>>,
+}
+
+impl<'a> TerminalFrontend<'a> {
+    pub fn new(options: &'a LessonOptions) -> crossterm::Result<TerminalFrontend<'a>> {
+        let alternate = AlternateScreen::to_alternate(true)?;
+        let raw = RawScreen::into_raw_mode();
+
+        Ok(TerminalFrontend {
+            _raw: raw,
+            _alternate: alternate,
+            stdout: stdout(),
+            stdin: input().read_async(),
+            layout: &options.layout,
+            show_keyboard: options.show_keyboard,
+            physical_layout: if options.qwerty_emulation {
+                Some(&*layout::QWERTY)
+            } else {
+                None
+            },
+            next_char: None,
+            target_row: 0,
+            time_limit: None,
+            highlight_syntax: options.highlight_syntax,
+            highlighted: None,
+        })
+    }
+
+    /// Reads the next input event, as typed. See `next_event` for the events of lessons.
+    fn read_event(&mut self) -> Option<Event> {
+        for event in &mut self.stdin {
+            match event {
+                InputEvent::Keyboard(KeyEvent::Char(character)) => {
+                    return Some(Event::InputCharacter(character))
+                }
+                InputEvent::Keyboard(KeyEvent::Esc) => return Some(Event::Quit),
+                InputEvent::Keyboard(KeyEvent::Ctrl('c')) => return Some(Event::Quit),
+
+                InputEvent::Keyboard(KeyEvent::Backspace) => return Some(Event::BackSpace),
+                InputEvent::Keyboard(KeyEvent::Enter) => return Some(Event::NewLine),
+                _ => {}
+            };
+        }
+        None
+    }
+
+    /// A character of the line being typed, in the color of its token if code is highlighted.
+    fn target_character(
+        &self,
+        session: &TypingSession,
+        column: usize,
+        character: char,
+    ) -> StyledContent<char> {
+        let kind = self
+            .highlighted
+            .as_ref()
+            .and_then(|lines| lines.get(session.line_index())?.get(column));
+
+        match kind {
+            Some(TokenKind::Keyword) => style(character).magenta(),
+            Some(TokenKind::String) => style(character).green(),
+            Some(TokenKind::Number) => style(character).cyan(),
+            Some(TokenKind::Comment) => style(character).dark_grey(),
+            Some(TokenKind::Plain) | None => style(character),
+        }
+    }
+
+    /// Writes the line being typed from `column` on, up to `end`.
+    fn write_target(
+        &mut self,
+        session: &TypingSession,
+        column: usize,
+        end: usize,
+    ) -> crossterm::Result<()> {
+        let line = session.line().unwrap_or_default();
+
+        for (index, &character) in line.iter().enumerate().take(end).skip(column) {
+            let content = self.target_character(session, index, character);
+            execute!(self.stdout, PrintStyledContent(content))?;
+        }
+        Ok(())
+    }
+
+    /// Reads the next key for the lesson browser.
+    fn read_browser_key(&mut self) -> Option<BrowserKey> {
+        for event in &mut self.stdin {
+            let key = match event {
+                InputEvent::Keyboard(KeyEvent::Up) => BrowserKey::Up,
+                InputEvent::Keyboard(KeyEvent::Down) => BrowserKey::Down,
+                InputEvent::Keyboard(KeyEvent::PageUp) => BrowserKey::PageUp,
+                InputEvent::Keyboard(KeyEvent::PageDown) => BrowserKey::PageDown,
+                InputEvent::Keyboard(KeyEvent::Char(character)) => BrowserKey::Character(character),
+                InputEvent::Keyboard(KeyEvent::Backspace) => BrowserKey::Backspace,
+                InputEvent::Keyboard(KeyEvent::Enter) => BrowserKey::Enter,
+                InputEvent::Keyboard(KeyEvent::Esc) => BrowserKey::Escape,
+                InputEvent::Keyboard(KeyEvent::Ctrl('c')) => BrowserKey::Escape,
+                _ => continue,
+            };
+            return Some(key);
+        }
+        None
+    }
+
+    /// Shows the lesson browser until the user picks a lesson, returning its id, or quits.
+    pub fn browse(
+        &mut self,
+        browser: &mut LessonBrowser,
+        lessons: &IndexMap<String, Lesson>,
+    ) -> crossterm::Result<Option<String>> {
+        self.draw_browser(browser, lessons)?;
+
+        loop {
+            let key = match self.read_browser_key() {
+                Some(key) => key,
+                None => {
+                    thread::sleep(Duration::from_millis(10));
+                    continue;
+                }
+            };
+
+            match browser.handle(key) {
+                BrowserAction::Moved => self.draw_browser(browser, lessons)?,
+                BrowserAction::Open(lesson_id) => return Ok(Some(lesson_id)),
+                BrowserAction::Quit => return Ok(None),
+            }
+        }
+    }
+
+    /// Draws the filter on top, the list of lessons on the left, the selected lesson on the
+    /// right and help on the bottom row.
+    fn draw_browser(
+        &mut self,
+        browser: &LessonBrowser,
+        lessons: &IndexMap<String, Lesson>,
+    ) -> crossterm::Result<()> {
+        let (width, height) = size()?;
+        let (width, height) = (usize::from(width), usize::from(height));
+        let list_width = (width / 2).min(64);
+        let list_height = height.saturating_sub(3);
+
+        execute!(
+            self.stdout,
+            Hide,
+            Clear(ClearType::All),
+            MoveTo(0, 0),
+            Output(fit(&format!("Filter: {}", browser.filter()), width))
+        )?;
+
+        let visible = browser.visible();
+        let selected = browser.selected_index();
+        // scroll just far enough to keep the selection in view
+        let first = (selected + 1).saturating_sub(list_height);
+
+        for (row, entry) in visible.iter().enumerate().skip(first).take(list_height) {
+            let (mark, result) = match &entry.progress {
+                Some(progress) if progress.completed => (
+                    "[x]",
+                    format!(
+                        "{:>4.0} WPM {:>5.1}%",
+                        progress.best_net_wpm, progress.best_accuracy
+                    ),
+                ),
+                Some(_) => ("[-]", String::new()),
+                None => ("[ ]", String::new()),
+            };
+            let title_width = list_width.saturating_sub(result.len() + 5);
+            let text = format!("{} {} {}", mark, fit(&entry.title, title_width), result);
+
+            let content = if row == selected {
+                style(fit(&text, list_width)).black().on_white()
+            } else {
+                style(fit(&text, list_width))
+            };
+            execute!(
+                self.stdout,
+                MoveTo(0, (row - first + 2) as u16),
+                PrintStyledContent(content)
+            )?;
+        }
+
+        if visible.is_empty() {
+            execute!(
+                self.stdout,
+                MoveTo(0, 2),
+                Output("No lesson matches the filter")
+            )?;
+        }
+
+        let selected_lesson = browser
+            .selected()
+            .and_then(|entry| lessons.get(entry.id.as_str()));
+        if let Some(lesson) = selected_lesson {
+            self.draw_preview(lesson, list_width + 2, width, list_height)?;
+        }
+
+        execute!(
+            self.stdout,
+            MoveTo(0, height.saturating_sub(1) as u16),
+            PrintStyledContent(
+                style(fit(
+                    "Up/Down: select  Enter: start  Type: filter  Esc: clear filter, quit",
+                    width
+                ))
+                .black()
+                .on_white()
+            )
+        )
+    }
+
+    /// Shows the title, description and practice text of a lesson from column `left` on.
+    fn draw_preview(
+        &mut self,
+        lesson: &Lesson,
+        left: usize,
+        width: usize,
+        height: usize,
+    ) -> crossterm::Result<()> {
+        let pane_width = width.saturating_sub(left);
+        if pane_width == 0 {
+            return Ok(());
+        }
+
+        let mut lines = vec![lesson.title().to_string()];
+        if let Some(description) = &lesson.meta().description {
+            lines.extend(text::practice_lines(description, pane_width));
+        }
+        lines.push(String::new());
+        lines.extend(lesson.text().lines().map(str::to_string));
+
+        for (row, line) in lines.iter().take(height).enumerate() {
+            execute!(
+                self.stdout,
+                MoveTo(left as u16, (row + 2) as u16),
+                Output(fit(line, pane_width))
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_character(&mut self, current_char: char, input_char: char) -> crossterm::Result<()> {
+        let content = if current_char == input_char {
+            style(input_char).black().on_green()
+        } else {
+            style(input_char).black().on_red()
+        };
+
+        execute!(self.stdout, PrintStyledContent(content))
+    }
+
+    /// Rows at the bottom of the terminal that practice text must stay out of.
+    fn footer_height(&self) -> u16 {
+        if self.show_keyboard {
+            keyboard::KEYBOARD_HEIGHT + 1
+        } else {
+            1
+        }
+    }
+
+    /// Moves the cursor to the start of the next row, scrolling the practice text up when
+    /// the row below would be part of the footer.
+    fn next_row(&mut self, metrics: &SessionMetrics) -> crossterm::Result<()> {
+        let footer_top = size()?.1.saturating_sub(self.footer_height());
+        let row = position()?.1;
+
+        if row + 1 < footer_top {
+            return execute!(self.stdout, MoveTo(0, row + 1));
+        }
+
+        // clear the footer first so it isn't dragged up with the practice text
+        execute!(
+            self.stdout,
+            MoveTo(0, footer_top),
+            Clear(ClearType::FromCursorDown),
+            MoveTo(0, row),
+            ScrollUp(1),
+            MoveTo(0, row)
+        )?;
+
+        self.draw_footer(metrics)
+    }
+
+    /// Redraws the keyboard, if shown, and the live metrics on the bottom row.
+    fn draw_footer(&mut self, metrics: &SessionMetrics) -> crossterm::Result<()> {
+        let (width, height) = size()?;
+        let status_row = height.saturating_sub(1);
+
+        execute!(self.stdout, SavePosition)?;
+
+        if self.show_keyboard {
+            let top = height.saturating_sub(self.footer_height());
+            self.draw_keyboard(top)?;
+        }
+
+        let now = Instant::now();
+        let mut status = metrics.status_line(now);
+        if let Some(limit) = self.time_limit {
+            // rounded up, so the time left only shows 0:00 once it's up
+            let left = limit.checked_sub(metrics.elapsed(now)).unwrap_or_default();
+            let left = Duration::from_secs(left.as_secs() + u64::from(left.subsec_nanos() > 0));
+            status = format!("{}  Left: {}", status, format_duration(left));
+        }
+        // writing the last column of the bottom row would scroll the screen
+        let status = fit(&status, usize::from(width).saturating_sub(1));
+
+        execute!(
+            self.stdout,
+            MoveTo(0, status_row),
+            Clear(ClearType::CurrentLine),
+            PrintStyledContent(style(status).black().on_white()),
+            RestorePosition
+        )
+    }
+
+    fn draw_keyboard(&mut self, top: u16) -> crossterm::Result<()> {
+        let layout = self.layout;
+
+        execute!(
+            self.stdout,
+            MoveTo(0, top),
+            Clear(ClearType::CurrentLine),
+            Output(keyboard::hint(layout, self.next_char))
+        )?;
+
+        for (index, cells) in keyboard::diagram(layout, self.next_char)
+            .into_iter()
+            .enumerate()
+        {
+            execute!(
+                self.stdout,
+                MoveTo(0, top + 1 + index as u16),
+                Clear(ClearType::CurrentLine)
+            )?;
+
+            for cell in cells {
+                let content = match cell.highlight {
+                    Highlight::None => style(cell.label),
+                    Highlight::Target => style(cell.label).black().on_green(),
+                    Highlight::Finger => style(cell.label).black().on_yellow(),
+                    Highlight::Shift => style(cell.label).black().on_cyan(),
+                };
+                execute!(self.stdout, PrintStyledContent(content))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> Frontend for TerminalFrontend<'a> {
+    type Error = crossterm::ErrorKind;
+
+    fn start_lesson(&mut self, lesson: &Lesson) -> crossterm::Result<()> {
+        let metrics = SessionMetrics::new();
+        self.time_limit = TypingSession::new(lesson).time_limit();
+        self.highlighted = match &lesson.meta().language {
+            Some(language) if self.highlight_syntax => language
+                .parse::<Language>()
+                .ok()
+                .map(|language| code::highlight(language, lesson.text())),
+            _ => None,
+        };
+
+        execute!(self.stdout, Hide, Clear(ClearType::All))?;
+
+        execute!(
+            self.stdout,
+            MoveTo(0, 0),
+            Output(lesson.title().to_string()),
+            MoveTo(0, 1)
+        )?;
+        self.draw_footer(&metrics)?;
+
+        if let Some(description) = &lesson.meta().description {
+            execute!(self.stdout, Output(description.clone()))?;
+            self.next_row(&metrics)?;
+        }
+
+        Ok(())
+    }
+
+    fn show_line(&mut self, session: &TypingSession) -> crossterm::Result<()> {
+        let length = session.line().unwrap_or_default().len();
+
+        self.write_target(session, 0, length)?;
+        self.next_row(session.metrics())?;
+
+        self.target_row = position()?.1.saturating_sub(1);
+
+        // indentation typed automatically
+        let typed: String = session.typed().iter().collect();
+        execute!(self.stdout, Output(typed))
+    }
+
+    fn show_update(&mut self, session: &TypingSession, update: Update) -> crossterm::Result<()> {
+        match update {
+            Update::Typed(keystroke) => self.write_character(keystroke.expected, keystroke.typed),
+            Update::Erased => execute!(self.stdout, MoveLeft(1), Clear(ClearType::UntilNewLine)),
+            Update::NextLine | Update::Completed | Update::TimeUp => {
+                self.next_row(session.metrics())
+            }
+            Update::Ignored | Update::Quit => Ok(()),
+        }
+    }
+
+    fn show_status(&mut self, session: &TypingSession) -> crossterm::Result<()> {
+        // at the end of the line the next key is enter, which isn't on the diagram
+        self.next_char = session.next_char();
+        self.draw_footer(session.metrics())
+    }
+
+    fn show_ghost(
+        &mut self,
+        session: &TypingSession,
+        ghost: GhostPosition,
+    ) -> crossterm::Result<()> {
+        let line = session.line().unwrap_or_default();
+
+        execute!(self.stdout, SavePosition, MoveTo(0, self.target_row))?;
+
+        if ghost.line == session.line_index() {
+            // the ghost's cursor is the character it types next, or past the end of the line
+            let column = ghost.column.min(line.len());
+            let cursor = line.get(column).cloned().unwrap_or(' ');
+
+            self.write_target(session, 0, column)?;
+            execute!(
+                self.stdout,
+                PrintStyledContent(style(cursor).black().on_blue())
+            )?;
+            self.write_target(session, column + 1, line.len())?;
+        } else {
+            let note = if ghost.line >= session.lines().len() {
+                "  ghost finished".to_string()
+            } else if ghost.line > session.line_index() {
+                let distance = ghost.line - session.line_index();
+                format!("  ghost {} {} ahead", distance, plural_lines(distance))
+            } else {
+                let distance = session.line_index() - ghost.line;
+                format!("  ghost {} {} behind", distance, plural_lines(distance))
+            };
+
+            self.write_target(session, 0, line.len())?;
+            execute!(self.stdout, PrintStyledContent(style(note).blue()))?;
+        }
+
+        execute!(self.stdout, Clear(ClearType::UntilNewLine), RestorePosition)
+    }
+
+    /// When emulating, typed characters are translated from the key position they have on the
+    /// physical layout to the character at the same position on the layout being learned.
+    /// Answers to prompts aren't, they are read as typed.
+    fn next_event(&mut self) -> crossterm::Result<Option<Event>> {
+        let event = match (self.read_event(), self.physical_layout) {
+            (Some(Event::InputCharacter(character)), Some(physical)) => Some(
+                Event::InputCharacter(self.layout.translate(character, physical)),
+            ),
+            (event, _) => event,
+        };
+        Ok(event)
+    }
+
+    fn show_summary(
+        &mut self,
+        metrics: &SessionMetrics,
+        failures: &[String],
+    ) -> crossterm::Result<()> {
+        self.next_char = None;
+
+        self.draw_footer(metrics)?;
+        self.next_row(metrics)?;
+
+        for summary_line in metrics.summary(Instant::now()) {
+            execute!(self.stdout, Output(summary_line))?;
+            self.next_row(metrics)?;
+        }
+
+        for failure in failures {
+            execute!(
+                self.stdout,
+                PrintStyledContent(style(failure.clone()).red())
+            )?;
+            self.next_row(metrics)?;
+        }
+
+        Ok(())
+    }
+
+    fn ask_next_lesson(&mut self, passed: bool) -> crossterm::Result<bool> {
+        let question = format!("{} ", next_lesson_question(passed));
+        execute!(self.stdout, Output(question))?;
+
+        let start_x = position()?.0;
+
+        let mut answer = false;
+
+        loop {
+            match self.read_event() {
+                Some(Event::InputCharacter(mut character)) => {
+                    character.make_ascii_lowercase();
+                    if let 'y' | 'n' = character {
+                        if position()?.0 > start_x {
+                            execute!(self.stdout, MoveLeft(1), Clear(ClearType::UntilNewLine))?;
+                        }
+                        execute!(self.stdout, Output(character))?;
+
+                        answer = character == 'y';
+                    }
+                }
+
+                Some(Event::NewLine) => break,
+
+                Some(Event::Quit) => {
+                    answer = false;
+                    break;
+                }
+
+                _ => {}
+            }
+        }
+
+        Ok(answer)
+    }
+}
+
+/// Cuts `text` to `width` characters, or pads it with spaces to that width.
+fn fit(text: &str, width: usize) -> String {
+    let mut fitted: String = text.chars().take(width).collect();
+    let length = fitted.chars().count();
+    fitted.push_str(&" ".repeat(width - length));
+    fitted
+}
+
+fn plural_lines(count: usize) -> &'static str {
+    if count == 1 {
+        "line"
+    } else {
+        "lines"
+    }
+}
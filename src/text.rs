@@ -0,0 +1,107 @@
+use crate::{Lesson, LessonMeta};
+
+/// Tab stops are this many columns apart when tabs are expanded to spaces.
+pub const TAB_WIDTH: usize = 4;
+
+/// Wraps arbitrary text, like a source file, in a lesson for `--text` and `--stdin`. See
+/// `practice_lines` for how the text is prepared.
+pub fn text_lesson(title: &str, source: &str, width: usize) -> Lesson {
+    Lesson::new(
+        "text".to_string(),
+        title.to_string(),
+        practice_lines(source, width).join("\n"),
+        LessonMeta::default(),
+    )
+}
+
+/// Turns text into lines that can be typed and fit in `width` columns.
+///
+/// Tabs are expanded to the next tab stop. Other control characters, like carriage returns,
+/// are dropped along with terminal escape sequences. Trailing whitespace can't be seen, so
+/// it's removed along with the empty lines it leaves. Longer lines are wrapped at the last
+/// space that fits, or anywhere in words that don't fit on a line of their own.
+pub fn practice_lines(source: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+
+    source
+        .lines()
+        .map(clean_line)
+        .filter(|line| !line.is_empty())
+        .flat_map(|line| wrap(&line, width))
+        .collect()
+}
+
+/// Expands the tabs of a line and drops control characters and trailing whitespace, see
+/// `practice_lines`.
+pub fn clean_line(line: &str) -> String {
+    let mut cleaned = String::new();
+    let mut column = 0;
+    let mut characters = line.chars().peekable();
+
+    while let Some(character) = characters.next() {
+        if character == '\x1b' && characters.peek() == Some(&'[') {
+            // colors and other escape sequences, from piping in a colored diff for example
+            characters.next();
+            for parameter in characters.by_ref() {
+                if ('@'..='~').contains(&parameter) {
+                    break;
+                }
+            }
+        } else if character == '\t' {
+            let spaces = TAB_WIDTH - column % TAB_WIDTH;
+            cleaned.push_str(&" ".repeat(spaces));
+            column += spaces;
+        } else if !character.is_control() {
+            cleaned.push(character);
+            column += 1;
+        }
+    }
+
+    cleaned.trim_end().to_string()
+}
+
+fn wrap(line: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut rest: Vec<char> = line.chars().collect();
+
+    while rest.len() > width {
+        // a space inside the indentation isn't a place to break
+        let indent = rest.iter().take_while(|&&c| c == ' ').count();
+        let (end, next) = match rest[..=width].iter().rposition(|&c| c == ' ') {
+            Some(space) if space > indent => (space, space + 1),
+            _ => (width, width),
+        };
+
+        lines.push(
+            rest[..end]
+                .iter()
+                .collect::<String>()
+                .trim_end()
+                .to_string(),
+        );
+        rest.drain(..next);
+        let spaces = rest.iter().take_while(|&&c| c == ' ').count();
+        rest.drain(..spaces);
+    }
+
+    if !rest.is_empty() {
+        lines.push(rest.into_iter().collect());
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prepares_arbitrary_text_for_typing() {
+        let source = "fn main() {\r\n\tprintln!(\"hello world\");\x1b[0m   \n\n}\n";
+
+        assert_eq!(
+            practice_lines(source, 20),
+            vec!["fn main() {", "    println!(\"hello", "world\");", "}"]
+        );
+        assert_eq!(practice_lines("abcdefgh", 3), vec!["abc", "def", "gh"]);
+    }
+}
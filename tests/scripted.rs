@@ -0,0 +1,402 @@
+use programmer_dvorak_tutor::code::{code_lesson, snippets, Language};
+use programmer_dvorak_tutor::ghost::{Ghost, GhostPosition, RaceAgainst};
+use programmer_dvorak_tutor::script::{ScriptedFrontend, DEFAULT_INTERVAL};
+use programmer_dvorak_tutor::session::missed_characters;
+use programmer_dvorak_tutor::{run_lesson, Event, Lesson, LessonMeta, Stats};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+fn lesson(text: &str) -> Lesson {
+    Lesson::new(
+        "99".to_string(),
+        "Test lesson".to_string(),
+        text.to_string(),
+        LessonMeta::default(),
+    )
+}
+
+fn typing(text: &str) -> Vec<Event> {
+    text.chars().map(Event::InputCharacter).collect()
+}
+
+/// A script typing every practice line of a bundled lesson without mistakes.
+fn lesson_script(file_name: &str) -> String {
+    let source = fs::read_to_string(format!("lessons/{}", file_name)).unwrap();
+    let mut script = String::new();
+    for line in source.lines().skip(1) {
+        script.push_str(&format!("type {}\nenter\n", line));
+    }
+    script
+}
+
+/// An empty directory to run the tutor in, so stats.json starts out missing.
+fn work_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("tutor-test-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// The tutor run in `dir`, with the configuration directory in it too so the user's own
+/// lessons and course don't change the results.
+fn tutor(dir: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_programmer_dvorak_tutor_cli"));
+    command
+        .current_dir(dir)
+        .env("HOME", dir)
+        .env("XDG_CONFIG_HOME", dir.join("config"))
+        .env_remove("APPDATA");
+    command
+}
+
+fn run_cli(dir: &Path, script: &str, args: &[&str]) -> Stats {
+    fs::write(dir.join("input.script"), script).unwrap();
+    let status = tutor(dir)
+        .arg("--script")
+        .arg("input.script")
+        .args(args)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    serde_json::from_str(&fs::read_to_string(dir.join("stats.json")).unwrap()).unwrap()
+}
+
+#[test]
+fn completes_a_lesson_and_answers_the_prompt() {
+    let mut events = typing("ab c");
+    events.push(Event::NewLine);
+    events.extend(typing("dx"));
+    events.push(Event::BackSpace);
+    events.extend(typing("e"));
+    events.push(Event::NewLine);
+    events.extend(typing("ny"));
+    events.push(Event::NewLine);
+
+    let mut frontend = ScriptedFrontend::from_events(events);
+    let mut stats = Stats::default();
+    let outcome = run_lesson(&mut frontend, &lesson("ab c\nde"), &mut stats, None).unwrap();
+
+    assert!(outcome.completed);
+    assert!(outcome.next);
+    assert!(frontend.is_finished());
+    assert_eq!(outcome.metrics.correct_keystrokes(), 6);
+    assert_eq!(outcome.metrics.corrected_errors(), 1);
+    assert_eq!(outcome.metrics.uncorrected_errors(), 0);
+    // 7 characters, a backspace and 2 new lines, one interval apart
+    assert_eq!(
+        outcome.metrics.elapsed(outcome.metrics.end().unwrap()),
+        DEFAULT_INTERVAL * 9
+    );
+
+    assert_eq!(stats.keys[&'e'].misses, 1);
+    assert_eq!(stats.keys[&'e'].substitutions[&'x'], 1);
+    assert_eq!(stats.bigrams["de"].attempts(), 2);
+    assert_eq!(
+        &frontend.transcript()[..3],
+        [
+            "Test lesson".to_string(),
+            "ab c".to_string(),
+            "de".to_string()
+        ]
+    );
+}
+
+#[test]
+fn running_out_of_events_quits() {
+    let mut frontend = ScriptedFrontend::from_events(typing("ab"));
+    let mut stats = Stats::default();
+    let outcome = run_lesson(&mut frontend, &lesson("abc"), &mut stats, None).unwrap();
+
+    assert!(!outcome.completed);
+    assert!(!outcome.next);
+    assert_eq!(outcome.metrics.total_keystrokes(), 2);
+}
+
+#[test]
+fn continue_starts_after_the_last_completed_lesson() {
+    let dir = work_dir("continue");
+
+    // finish lesson 01, go on to lesson 02 and give up on it
+    let script = format!(
+        "{}type y\nenter\ntype e\nquit\n",
+        lesson_script("lesson_01.txt")
+    );
+    let stats = run_cli(&dir, &script, &["--lesson", "01"]);
+
+    assert_eq!(stats.last_lesson_id, "01");
+    assert!(stats.lessons["01"].completed);
+    assert_eq!(stats.lessons["01"].best_accuracy, 100.0);
+    assert!(!stats.lessons["02"].completed);
+
+    let script = format!("{}type n\nenter\n", lesson_script("lesson_02.txt"));
+    let stats = run_cli(&dir, &script, &["--continue"]);
+
+    assert_eq!(stats.last_lesson_id, "02");
+    assert_eq!(stats.lessons["02"].attempts, 2);
+    assert!(stats.lessons["02"].completed);
+    assert_eq!(stats.lessons["01"].attempts, 1);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn logs_keystrokes_next_to_the_stats() {
+    let dir = work_dir("log");
+    run_cli(
+        &dir,
+        "type ux\n@2000 backspace\nquit\n",
+        &["--lesson", "01", "--log-keystrokes"],
+    );
+
+    let log = fs::read_to_string(dir.join("keystrokes.jsonl")).unwrap();
+    let lines: Vec<serde_json::Value> = log
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0]["lesson"], "01");
+    assert_eq!(lines[0]["t"], 0);
+    assert_eq!(lines[1]["event"]["input_character"], "x");
+    assert_eq!(lines[1]["expected"], "u");
+    assert_eq!(lines[2]["event"], "backspace");
+    assert_eq!(lines[2]["t"], 1_750_000);
+    assert_eq!(lines[3]["event"], "quit");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn ghosts_follow_the_pace_of_a_recorded_run() {
+    let lesson = lesson("ab\ncd");
+    let mut runs = Vec::new();
+
+    // the second run waits with a stray backspace before typing, which doesn't count
+    for text in ["ab\ncd\n", "\u{8}ab\ncx\u{8}d\n", "ab\nc"] {
+        let events = text.chars().map(|character| match character {
+            '\n' => Event::NewLine,
+            '\u{8}' => Event::BackSpace,
+            character => Event::InputCharacter(character),
+        });
+        let mut frontend = ScriptedFrontend::from_events(events);
+        let outcome = run_lesson(&mut frontend, &lesson, &mut Stats::default(), None).unwrap();
+        runs.push(outcome.events);
+    }
+
+    let best = Ghost::choose(&lesson, &runs, RaceAgainst::Best).unwrap();
+    assert_eq!(best.duration(), DEFAULT_INTERVAL * 5);
+    assert_eq!(
+        best.position(DEFAULT_INTERVAL),
+        GhostPosition { line: 0, column: 2 }
+    );
+    assert_eq!(
+        best.position(DEFAULT_INTERVAL * 3),
+        GhostPosition { line: 1, column: 1 }
+    );
+
+    // the last run quit halfway, so the one before it is raced
+    let last = Ghost::choose(&lesson, &runs, RaceAgainst::Last).unwrap();
+    assert_eq!(last.duration(), DEFAULT_INTERVAL * 7);
+    assert_eq!(
+        last.position(DEFAULT_INTERVAL * 4),
+        GhostPosition { line: 1, column: 2 }
+    );
+    assert_eq!(
+        last.position(DEFAULT_INTERVAL * 5),
+        GhostPosition { line: 1, column: 1 }
+    );
+}
+
+#[test]
+fn timed_lessons_end_when_the_time_is_up() {
+    let meta = LessonMeta {
+        time_limit: Some(1.0),
+        ..LessonMeta::default()
+    };
+    let lesson = Lesson::new(
+        "timed".to_string(),
+        "Timed test".to_string(),
+        "ab c\nde".to_string(),
+        meta,
+    );
+
+    // the clock starts with the first keystroke, so the new line comes a second later
+    let mut events = typing("abxc");
+    events.push(Event::NewLine);
+    events.extend(typing("de"));
+
+    let mut frontend = ScriptedFrontend::from_events(events);
+    let mut stats = Stats::default();
+    let outcome = run_lesson(&mut frontend, &lesson, &mut stats, None).unwrap();
+
+    assert!(outcome.completed);
+    assert_eq!(outcome.events.len(), 4);
+    assert_eq!(
+        outcome.metrics.elapsed(outcome.metrics.end().unwrap()),
+        Duration::from_secs(1)
+    );
+    assert_eq!(missed_characters(&outcome.events), vec![(' ', 1)]);
+}
+
+#[test]
+fn code_snippets_have_their_indentation_typed() {
+    let source = "// Copyright notice\n// of the file\n\nfn main() {\n\tif true {\n\t\tprintln!(\"x\");\n\t}\n}\n";
+    let snippets = snippets(Language::Rust, source, 80);
+
+    assert_eq!(snippets.len(), 1);
+    assert_eq!(snippets[0].line, 4);
+
+    let lesson = code_lesson(Language::Rust, "main.rs:4".to_string(), &snippets[0]);
+    let mut events = Vec::new();
+    for line in snippets[0].text.lines() {
+        events.extend(typing(line.trim_start()));
+        events.push(Event::NewLine);
+    }
+    events.push(Event::NewLine);
+
+    let mut frontend = ScriptedFrontend::from_events(events);
+    let mut stats = Stats::default();
+    let outcome = run_lesson(&mut frontend, &lesson, &mut stats, None).unwrap();
+
+    assert!(outcome.completed);
+    assert_eq!(outcome.metrics.incorrect_keystrokes(), 0);
+}
+
+#[test]
+fn failed_lessons_are_tried_again() {
+    let dir = work_dir("mastery");
+
+    // a typo fails lesson 01, the second try passes
+    let script = format!(
+        "{}type y\nenter\n{}type n\nenter\n",
+        lesson_script("lesson_01.txt").replacen("type u", "type x", 1),
+        lesson_script("lesson_01.txt")
+    );
+    let stats = run_cli(&dir, &script, &["--lesson", "01", "--min-accuracy", "100"]);
+
+    assert_eq!(stats.last_lesson_id, "01");
+    assert_eq!(stats.lessons["01"].attempts, 2);
+    assert!(stats.lessons["01"].completed);
+    assert!(!stats.lessons.contains_key("02"));
+
+    let script = format!("{}type n\nenter\n", lesson_script("lesson_02.txt"));
+    let stats = run_cli(&dir, &script, &["--continue", "--max-errors", "0"]);
+
+    assert_eq!(stats.lessons["02"].attempts, 1);
+    assert!(stats.lessons["02"].completed);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn piped_text_is_practiced_without_a_terminal() {
+    use std::io::Write;
+    use std::os::unix::process::CommandExt;
+    use std::process::Stdio;
+
+    let dir = work_dir("stdin");
+    fs::write(
+        dir.join("input.script"),
+        "type ab cd\nenter\ntype n\nenter\n",
+    )
+    .unwrap();
+
+    let mut command = tutor(&dir);
+    command
+        .args(["--script", "input.script", "--stdin"])
+        .stdin(Stdio::piped());
+    // a new session has no controlling terminal, like a CI job
+    unsafe {
+        command.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+    let mut child = command.spawn().unwrap();
+    child.stdin.take().unwrap().write_all(b"ab cd\n").unwrap();
+    assert!(child.wait().unwrap().success());
+
+    let stats: Stats =
+        serde_json::from_str(&fs::read_to_string(dir.join("stats.json")).unwrap()).unwrap();
+    assert_eq!(stats.keys[&'c'].hits, 1);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn validate_reports_lesson_problems_with_their_lines() {
+    let dir = work_dir("validate");
+    let lessons = dir.join("my-lessons");
+    fs::create_dir_all(lessons.join("unit1")).unwrap();
+    fs::write(lessons.join("lesson_90.txt"), "Lesson 90\nuh uh \nzu\tuh\n").unwrap();
+    fs::write(
+        lessons.join("lesson_91.txt"),
+        "---\ntitle: Lesson 91\nkeys: z\n---\nzu zu\nzu caf\u{e9}\n",
+    )
+    .unwrap();
+    fs::write(lessons.join("unit1").join("lesson_x y.txt"), "Bad\nuh\n").unwrap();
+    // lessons for other layouts are checked too
+    fs::write(
+        lessons.join("lesson_95.txt"),
+        "---\ntitle: Colemak\nlayout: colemak\n---\nars \nt\td\n",
+    )
+    .unwrap();
+
+    let output = tutor(&dir)
+        .args(["validate", "my-lessons", "--width", "5"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Found 9 problems in the lessons\n"
+    );
+
+    let output = String::from_utf8(output.stdout).unwrap();
+    let problems: Vec<&str> = output
+        .lines()
+        .map(|line| line.trim_start_matches("my-lessons/"))
+        .collect();
+    assert_eq!(
+        problems,
+        vec![
+            "unit1/lesson_x y.txt: lesson id \"unit1/x y\" contains \" \", only letters, digits, - and _ are allowed",
+            "lesson_90.txt:2: trailing whitespace can't be seen",
+            "lesson_90.txt:2: the line is 6 characters wide, more than 5",
+            "lesson_90.txt:3: tabs can't be told apart from spaces",
+            "lesson_90.txt:3: 'z' is used before lesson 91 introduces it",
+            "lesson_91.txt:6: 'é' isn't a printable ASCII character",
+            "lesson_91.txt:6: the line is 7 characters wide, more than 5",
+            "lesson_95.txt:5: trailing whitespace can't be seen",
+            "lesson_95.txt:6: tabs can't be told apart from spaces",
+        ]
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn usage_errors_exit_with_an_error_status() {
+    let dir = work_dir("usage-errors");
+    fs::write(dir.join("empty.txt"), " \n").unwrap();
+
+    for args in [
+        &["--script", "missing.script", "--lesson", "01"][..],
+        &["--layout", "no-such-layout", "--list"],
+        &["--min-wpm", "fast", "--list"],
+        &["--lesson", "999"],
+        &["--timed", "5", "--word-list", "empty.txt"],
+        &["--timed", "inf"],
+        &["--timed", "1e300"],
+        &["validate", "--width", "wide"],
+    ] {
+        let status = tutor(&dir).args(args).status().unwrap();
+        assert_eq!(status.code(), Some(1), "{:?}", args);
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}